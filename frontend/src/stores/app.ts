@@ -7,10 +7,36 @@ interface AppConfig {
 	songs_path: string | null
 }
 
+export type IndexingPhase = 'walk' | 'parse' | 'cluster' | 'save'
+
 export interface IndexingProgress {
-	progress: number
+	phase: IndexingPhase
+	processed: number
 	total: number
-	message: string
+	throughput: number
+	eta_secs: number | null
+}
+
+export type IndexingOutcome = 'Completed' | 'Cancelled' | 'Failed'
+
+export interface IndexingIssue {
+	path: string
+	reason: { type: string; detail?: string }
+}
+
+export interface IndexingReport {
+	outcome: IndexingOutcome
+	error: string | null
+	started_at: number
+	duration_ms: number
+	total_files: number
+	parsed: number
+	reused: number
+	skipped: number
+	failed: number
+	removed: number
+	indexed: number
+	issues: IndexingIssue[]
 }
 
 export const useAppStore = defineStore('app', () => {
@@ -19,7 +45,8 @@ export const useAppStore = defineStore('app', () => {
 	const indexingStatus = ref('')
 	const statusIsError = ref(false)
 	const indexingProgress = ref<IndexingProgress | null>(null)
-	let pollingInterval: number | null = null
+	const lastReport = ref<IndexingReport | null>(null)
+	const indexOutdated = ref<string | null>(null)
 
 	const statusColor = computed(() => {
 		return statusIsError.value ? 'bg-red-900/50 text-red-400' : 'bg-osu-pink/20 text-osu-pink'
@@ -29,46 +56,80 @@ export const useAppStore = defineStore('app', () => {
 		try {
 			const [config, dbIndexed] = await Promise.all([invoke<AppConfig>('get_config'), invoke<boolean>('is_db_indexed')])
 			isConfigured.value = !!config.songs_path && dbIndexed
-		} catch (e) {
-			console.error('Failed to get config from backend:', e)
+			indexOutdated.value = null
+		} catch (e: any) {
+			if (e?.type === 'IndexOutdated') {
+				indexOutdated.value = e.payload
+			} else {
+				console.error('Failed to get config from backend:', e)
+			}
 			isConfigured.value = false
 		}
 	}
 
-	async function startIndexing(songs_path: string, limit: number | null) {
+	// the index was built by an older feature extractor, so every map has to be parsed again
+	async function rebuildIndex() {
+		const config = await invoke<AppConfig>('get_config')
+		if (config.songs_path) await startIndexing(config.songs_path, null, false)
+	}
+
+	async function startIndexing(songs_path: string, limit: number | null, incremental: boolean) {
 		isIndexing.value = true
 		indexingStatus.value = 'Initializing indexing...'
 		statusIsError.value = false
 		indexingProgress.value = null
 
-		pollingInterval = setInterval(async () => {
-			try {
-				indexingProgress.value = await invoke('get_indexing_status')
-			} catch (e) {
-				console.error('Failed to fetch indexing status', e)
-			}
-		}, 1000)
-
 		try {
-			await invoke('index', { songsPath: songs_path, limit: limit || null })
+			await invoke('index', { songsPath: songs_path, limit: limit || null, incremental })
 		} catch (e: any) {
 			indexingStatus.value = `An error occurred: ${e}`
 			statusIsError.value = true
-			if (pollingInterval) clearInterval(pollingInterval)
 			isIndexing.value = false
 			indexingProgress.value = null
 		}
 	}
 
-	listen<boolean>('state-reloaded', (event) => {
-		if (pollingInterval) clearInterval(pollingInterval)
-		pollingInterval = null
+	async function cancelIndexing() {
+		indexingStatus.value = 'Cancelling...'
+		try {
+			await invoke<boolean>('cancel_indexing')
+		} catch (e) {
+			console.error('Failed to cancel indexing', e)
+		}
+	}
+
+	async function loadLastReport() {
+		try {
+			lastReport.value = await invoke<IndexingReport | null>('get_indexing_report')
+		} catch (e) {
+			console.error('Failed to load indexing report', e)
+		}
+	}
+
+	listen<IndexingProgress>('indexing-progress', (event) => {
+		if (isIndexing.value) indexingProgress.value = event.payload
+	})
 
+	listen<IndexingReport>('index-complete', (event) => {
+		lastReport.value = event.payload
+	})
+
+	listen<boolean>('state-reloaded', (event) => {
 		const wasSuccessful = event.payload
-		if (wasSuccessful) {
+		const outcome = lastReport.value?.outcome
+		if (outcome === 'Cancelled') {
+			indexingStatus.value = 'Indexing cancelled. The previous database was kept.'
+			statusIsError.value = false
+			isConfigured.value = wasSuccessful
+		} else if (outcome === 'Failed') {
+			indexingStatus.value = `Indexing failed: ${lastReport.value?.error}`
+			statusIsError.value = true
+			isConfigured.value = wasSuccessful
+		} else if (wasSuccessful) {
 			indexingStatus.value = 'Indexing completed successfully! You can now use the search.'
 			statusIsError.value = false
 			isConfigured.value = true
+			indexOutdated.value = null
 		} else {
 			indexingStatus.value = 'Indexing finished, but failed to load the database.'
 			statusIsError.value = true
@@ -79,5 +140,24 @@ export const useAppStore = defineStore('app', () => {
 		indexingProgress.value = null
 	})
 
-	return { isConfigured, isIndexing, indexingStatus, statusIsError, indexingProgress, statusColor, checkConfiguration, startIndexing }
+	// the Songs folder watcher re-indexed new/changed maps in the background
+	listen('index-updated', () => {
+		isConfigured.value = true
+	})
+
+	return {
+		isConfigured,
+		isIndexing,
+		indexingStatus,
+		lastReport,
+		indexOutdated,
+		statusIsError,
+		indexingProgress,
+		statusColor,
+		checkConfiguration,
+		startIndexing,
+		cancelIndexing,
+		rebuildIndex,
+		loadLastReport,
+	}
 })
@@ -0,0 +1,542 @@
+use anyhow::{bail, Context, Result};
+use app_lib::core::{
+    self,
+    features::shapes::{self, ShapeKind},
+    model::{Beatmap, IndexingOutcome, IndexingReport, MapInfo},
+    progress::{IndexingPhase, ProgressSink, ProgressUpdate},
+    searcher::SearchFilter,
+    storage::IndexFile,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use seli_vector_db::Vector;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(
+    name = "osu-explorer",
+    version,
+    about = "Find similar osu! beatmaps by mapping style"
+)]
+struct Cli {
+    /// Database file to use instead of the one in the app data directory
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parse every .osu file in the Songs folder and build the search index
+    Index {
+        /// osu! Songs folder (defaults to the configured or auto-detected one)
+        songs_path: Option<PathBuf>,
+        /// Only index the first N beatmaps
+        #[arg(short, long, conflicts_with = "incremental")]
+        limit: Option<usize>,
+        /// Only re-parse files that were added or changed since the last run
+        #[arg(short, long)]
+        incremental: bool,
+    },
+    /// Find maps similar to a beatmap id, an osu.ppy.sh link or a .osu file
+    Search {
+        /// Beatmap id, osu.ppy.sh link, path to a .osu file or `-` to read one from stdin
+        query: String,
+        /// Number of results to show
+        #[arg(short, default_value_t = 10)]
+        k: usize,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// List the patterns (squares, back-and-forths, zig-zags...) of a map with their timestamps
+    Patterns {
+        /// Beatmap id, osu.ppy.sh link, path to a .osu file or `-` to read one from stdin
+        query: String,
+    },
+    /// Show information about the current index
+    Stats,
+    /// Export the list of indexed maps
+    Export {
+        output: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+}
+
+/// Limits on the locally estimated difficulty of the results
+#[derive(Args)]
+struct FilterArgs {
+    /// Only show maps with at least this many stars
+    #[arg(long)]
+    min_stars: Option<f32>,
+    /// Only show maps with at most this many stars
+    #[arg(long)]
+    max_stars: Option<f32>,
+    /// Lowest aim rating
+    #[arg(long)]
+    min_aim: Option<f32>,
+    /// Highest aim rating
+    #[arg(long)]
+    max_aim: Option<f32>,
+    /// Lowest speed rating
+    #[arg(long)]
+    min_speed: Option<f32>,
+    /// Highest speed rating
+    #[arg(long)]
+    max_speed: Option<f32>,
+    /// Lowest max combo
+    #[arg(long)]
+    min_combo: Option<u32>,
+    /// Highest max combo
+    #[arg(long)]
+    max_combo: Option<u32>,
+}
+
+impl From<FilterArgs> for SearchFilter {
+    fn from(args: FilterArgs) -> Self {
+        SearchFilter {
+            min_stars: args.min_stars,
+            max_stars: args.max_stars,
+            min_aim: args.min_aim,
+            max_aim: args.max_aim,
+            min_speed: args.min_speed,
+            max_speed: args.max_speed,
+            min_combo: args.min_combo,
+            max_combo: args.max_combo,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+struct BarSink {
+    pb: ProgressBar,
+}
+
+impl ProgressSink for BarSink {
+    fn on_progress(&self, update: &ProgressUpdate) {
+        let phase = match update.phase {
+            IndexingPhase::Walk => "Scanning Songs folder...",
+            IndexingPhase::Parse => "Parsing beatmaps...",
+            IndexingPhase::Cluster => "Building search index...",
+            IndexingPhase::Save => "Saving database...",
+        };
+        self.pb.set_length(update.total.max(update.processed));
+        self.pb.set_position(update.processed);
+        self.pb
+            .set_message(format!("{} {:.0}/s", phase, update.throughput));
+    }
+}
+
+enum Query {
+    BeatmapId(i32),
+    File(PathBuf),
+    Stdin,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let db_path = match cli.db {
+        Some(path) => path,
+        None => core::config::data_dir()?.join("osu_maps.db"),
+    };
+
+    match cli.command {
+        Command::Index {
+            songs_path,
+            limit,
+            incremental,
+        } => run_index(songs_path, &db_path, limit, incremental),
+        Command::Search { query, k, filter } => run_search(&query, &db_path, k, &filter.into()),
+        Command::Patterns { query } => run_patterns(&query, &db_path),
+        Command::Stats => run_stats(&db_path),
+        Command::Export { output, format } => run_export(&db_path, &output, format),
+    }
+}
+
+fn run_index(
+    songs_path: Option<PathBuf>,
+    db_path: &Path,
+    limit: Option<usize>,
+    incremental: bool,
+) -> Result<()> {
+    let songs_path = match songs_path {
+        Some(path) => path,
+        None => core::config::load_or_detect_config()?
+            .songs_path
+            .context("No Songs folder configured or detected. Pass it as an argument.")?,
+    };
+    if !songs_path.is_dir() {
+        bail!("'{}' is not a directory.", songs_path.display());
+    }
+
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.magenta} [{elapsed_precise}] [{bar:40.magenta/white}] {pos}/{len} ({eta}) {msg}",
+        )?
+        .progress_chars("=> "),
+    );
+
+    let sink = BarSink { pb };
+    let result =
+        core::indexer::run_indexing(&songs_path, db_path, limit, incremental, Some(&sink), None);
+    sink.pb.finish_and_clear();
+    print_report(&result);
+
+    match result.outcome {
+        IndexingOutcome::Completed => {
+            println!("{}", "Indexing finished.".green().bold());
+            Ok(())
+        }
+        IndexingOutcome::Cancelled => bail!("Indexing was cancelled."),
+        IndexingOutcome::Failed => bail!(
+            "Indexing failed: {}",
+            result.error.as_deref().unwrap_or("unknown error")
+        ),
+    }
+}
+
+fn print_report(report: &IndexingReport) {
+    const MAX_LISTED_ISSUES: usize = 20;
+
+    println!(
+        "Parsed {}, unchanged {}, skipped {}, failed {}, removed {} ({:.1}s)",
+        report.parsed.to_string().green(),
+        report.reused,
+        report.skipped.to_string().yellow(),
+        report.failed.to_string().red(),
+        report.removed,
+        report.duration_ms as f64 / 1000.0
+    );
+    for issue in report.issues.iter().take(MAX_LISTED_ISSUES) {
+        let reason = issue.reason.to_string();
+        let reason = if issue.reason.is_skip() {
+            reason.yellow()
+        } else {
+            reason.red()
+        };
+        println!("  {} {}", issue.path.display().to_string().dimmed(), reason);
+    }
+    if report.issues.len() > MAX_LISTED_ISSUES {
+        println!(
+            "  ...and {} more, see the indexing report next to the database",
+            report.issues.len() - MAX_LISTED_ISSUES
+        );
+    }
+}
+
+fn run_search(raw_query: &str, db_path: &Path, k: usize, filter: &SearchFilter) -> Result<()> {
+    let IndexFile {
+        db,
+        map_infos,
+        vectors,
+        ..
+    } = load_database(db_path)?;
+
+    let (query_vector, exclude_index) = match parse_query(raw_query)? {
+        Query::BeatmapId(beatmap_id) => {
+            let index = map_infos
+                .iter()
+                .position(|info| info.beatmap_id == beatmap_id)
+                .with_context(|| format!("Beatmap {} is not in the index.", beatmap_id))?;
+            println!("Query map: '{}'", map_infos[index].path.display());
+            (vectors[index].clone(), Some(index))
+        }
+        Query::File(path) => {
+            println!("Analyzing query map: '{}'", path.display());
+            let beatmap = core::parser::parse_beatmap_from_file(&path)?;
+            let index = indexed_position(&map_infos, beatmap.beatmap_id);
+            (extract_query_vector(&beatmap)?, index)
+        }
+        Query::Stdin => {
+            println!("Analyzing query map from stdin...");
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            let beatmap = core::parser::parse_beatmap_from_str(&contents)?;
+            let index = indexed_position(&map_infos, beatmap.beatmap_id);
+            (extract_query_vector(&beatmap)?, index)
+        }
+    };
+
+    println!("\nSearching for the top {} similar maps...", k);
+    let search_results =
+        core::searcher::perform_search(&db, &map_infos, &query_vector, exclude_index, k, filter);
+
+    if search_results.is_empty() {
+        println!("No similar maps found.");
+        return Ok(());
+    }
+
+    println!("--- Search Results ---");
+    for (i, (score, map_info)) in search_results.iter().enumerate() {
+        let map_display = format!(
+            "{} - {} [{}]",
+            map_info.artist, map_info.title, map_info.difficulty_name
+        );
+
+        let divergence = (1.0 - score) * 100.0;
+        let formatted_divergence = format!("{:.2}", divergence);
+
+        let (label, colored_divergence) = if divergence <= 0.25 {
+            ("Close", formatted_divergence.green())
+        } else if divergence <= 1.0 {
+            ("Similar", formatted_divergence.yellow())
+        } else if divergence <= 2.0 {
+            ("Related", formatted_divergence.bright_yellow())
+        } else {
+            ("Distant", formatted_divergence.red())
+        };
+
+        let difficulty = &map_info.difficulty;
+        let settings = format!(
+            "{:.2}* CS{} AR{} OD{} HP{} {:.0}BPM",
+            map_info.attributes.star_rating,
+            difficulty.circle_size,
+            difficulty.approach_rate,
+            difficulty.overall_difficulty,
+            difficulty.hp_drain_rate,
+            map_info.timing.bpm
+        );
+
+        println!(
+            "{}. Score: {:.4} (Divergence: {}, {}) | {} {} ({})",
+            (i + 1).to_string().dimmed(),
+            score,
+            colored_divergence,
+            label,
+            map_display,
+            settings.dimmed(),
+            beatmap_url(map_info).cyan()
+        );
+    }
+
+    Ok(())
+}
+
+fn extract_query_vector(beatmap: &Beatmap) -> Result<Vector> {
+    core::features::extract_features(beatmap)
+        .context("Could not extract features from the query map.")
+}
+
+// WIP maps usually have beatmap_id 0, so only real ids can match an indexed map
+fn indexed_position(map_infos: &[MapInfo], beatmap_id: i32) -> Option<usize> {
+    if beatmap_id <= 0 {
+        return None;
+    }
+    map_infos
+        .iter()
+        .position(|info| info.beatmap_id == beatmap_id)
+}
+
+fn run_patterns(raw_query: &str, db_path: &Path) -> Result<()> {
+    let beatmap = match parse_query(raw_query)? {
+        Query::BeatmapId(beatmap_id) => {
+            let map_infos = load_database(db_path)?.map_infos;
+            let map_info = map_infos
+                .iter()
+                .find(|info| info.beatmap_id == beatmap_id)
+                .with_context(|| format!("Beatmap {} is not in the index.", beatmap_id))?;
+            core::parser::parse_beatmap_from_file(&map_info.path)?
+        }
+        Query::File(path) => core::parser::parse_beatmap_from_file(&path)?,
+        Query::Stdin => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            core::parser::parse_beatmap_from_str(&contents)?
+        }
+    };
+
+    let events = shapes::detect(&beatmap.hit_objects, beatmap.difficulty.circle_radius());
+    println!(
+        "{} - {} [{}]",
+        beatmap.artist, beatmap.title, beatmap.difficulty_name
+    );
+    if events.is_empty() {
+        println!("No patterns found.");
+        return Ok(());
+    }
+    for event in &events {
+        println!(
+            "{} - {}  {} ({} objects)",
+            format_timestamp(event.start_time).dimmed(),
+            format_timestamp(event.end_time).dimmed(),
+            event.kind,
+            event.object_count
+        );
+    }
+
+    println!("--- Share of objects ---");
+    let frequencies = shapes::frequencies(&events, beatmap.hit_objects.len());
+    for (kind, share) in ShapeKind::ALL.iter().zip(frequencies) {
+        if share > 0.0 {
+            println!("{:<15} {:.1}%", kind.to_string(), share * 100.0);
+        }
+    }
+
+    Ok(())
+}
+
+/// mm:ss:ms, the format the editor accepts for jumping to a time
+fn format_timestamp(time_ms: f32) -> String {
+    let ms = time_ms.max(0.0) as u64;
+    format!("{:02}:{:02}:{:03}", ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
+
+fn run_stats(db_path: &Path) -> Result<()> {
+    let IndexFile {
+        header,
+        db,
+        map_infos,
+        ..
+    } = load_database(db_path)?;
+
+    let beatmapsets: HashSet<i32> = map_infos.iter().map(|info| info.beatmapset_id).collect();
+    let db_size = fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+
+    println!("{}", "--- Index Stats ---".bold());
+    println!("Database:     {}", db_path.display());
+    println!(
+        "Format:       v{} (feature schema v{}, {} features, difficulty v{})",
+        header.format_version,
+        header.feature_schema_version,
+        header.feature_count,
+        header.difficulty_version
+    );
+    println!("Built at:     {} (unix time)", header.built_at);
+    println!(
+        "Size on disk: {:.2} MiB",
+        db_size as f64 / (1024.0 * 1024.0)
+    );
+    println!("Beatmaps:     {}", map_infos.len().to_string().green());
+    println!("Beatmapsets:  {}", beatmapsets.len().to_string().green());
+    println!("Vectors:      {}", db.len());
+    match db.num_clusters() {
+        Some(clusters) => println!("Clusters:     {}", clusters),
+        None => println!("Clusters:     {}", "index not built".yellow()),
+    }
+
+    Ok(())
+}
+
+fn run_export(db_path: &Path, output: &Path, format: ExportFormat) -> Result<()> {
+    let map_infos = load_database(db_path)?.map_infos;
+
+    let contents = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&map_infos)?,
+        ExportFormat::Csv => {
+            let mut csv = String::from(
+                "beatmap_id,beatmapset_id,artist,title,difficulty_name,cs,ar,od,hp,slider_multiplier,slider_tick_rate,bpm,min_bpm,max_bpm,meter,kiai_ratio,kiai_density,stars,aim,speed,max_combo,path\n",
+            );
+            for info in &map_infos {
+                let difficulty = &info.difficulty;
+                let timing = &info.timing;
+                let attributes = &info.attributes;
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                    info.beatmap_id,
+                    info.beatmapset_id,
+                    csv_field(&info.artist),
+                    csv_field(&info.title),
+                    csv_field(&info.difficulty_name),
+                    difficulty.circle_size,
+                    difficulty.approach_rate,
+                    difficulty.overall_difficulty,
+                    difficulty.hp_drain_rate,
+                    difficulty.slider_multiplier,
+                    difficulty.slider_tick_rate,
+                    timing.bpm,
+                    timing.min_bpm,
+                    timing.max_bpm,
+                    timing.meter,
+                    timing.kiai_ratio,
+                    timing.kiai_density,
+                    attributes.star_rating,
+                    attributes.aim,
+                    attributes.speed,
+                    attributes.max_combo,
+                    csv_field(&info.path.to_string_lossy()),
+                ));
+            }
+            csv
+        }
+    };
+
+    fs::write(output, contents)
+        .with_context(|| format!("Failed to write '{}'", output.display()))?;
+    println!(
+        "Exported {} maps to '{}'",
+        map_infos.len().to_string().green(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn load_database(db_path: &Path) -> Result<IndexFile> {
+    println!("Loading database from '{}'...", db_path.display());
+    core::storage::load(db_path)
+        .context("Failed to load database. Did you run the 'index' command first?")
+}
+
+// https://osu.ppy.sh/beatmapsets/SET_ID#osu/BEATMAP_ID
+// https://osu.ppy.sh/beatmaps/BEATMAP_ID
+// https://osu.ppy.sh/b/BEATMAP_ID
+fn parse_query(raw: &str) -> Result<Query> {
+    let raw = raw.trim();
+    if raw == "-" {
+        return Ok(Query::Stdin);
+    }
+    if let Ok(beatmap_id) = raw.parse::<i32>() {
+        return Ok(Query::BeatmapId(beatmap_id));
+    }
+
+    if raw.contains("osu.ppy.sh/") {
+        let id_part = ["#osu/", "/beatmaps/", "/b/"]
+            .iter()
+            .find_map(|marker| raw.split_once(marker).map(|(_, rest)| rest));
+        let beatmap_id = id_part.and_then(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<i32>().ok()
+        });
+        return match beatmap_id {
+            Some(id) => Ok(Query::BeatmapId(id)),
+            None => bail!("Could not find a beatmap id in '{}'", raw),
+        };
+    }
+
+    let path = PathBuf::from(raw);
+    if path.is_file() {
+        Ok(Query::File(path))
+    } else {
+        bail!(
+            "'{}' is not a beatmap id, an osu.ppy.sh link or a .osu file",
+            raw
+        )
+    }
+}
+
+fn beatmap_url(map_info: &MapInfo) -> String {
+    format!(
+        "https://osu.ppy.sh/beatmapsets/{}#osu/{}",
+        map_info.beatmapset_id, map_info.beatmap_id
+    )
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
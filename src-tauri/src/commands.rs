@@ -1,11 +1,17 @@
 use crate::core::{
     self,
     config::save_config,
-    model::{AppConfig, MapInfo},
+    model::{AppConfig, IndexingReport, MapInfo},
+    progress::{ProgressSink, ProgressUpdate},
+    searcher::SearchFilter,
 };
-use crate::{error::AppError, AppState, IndexingProgress};
+use crate::{error::AppError, AppState};
 use std::path::Path;
-use tauri::{Emitter, Manager, State};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 #[derive(serde::Serialize)]
 pub struct SearchResultItem {
@@ -20,40 +26,94 @@ pub fn get_config(state: State<AppState>) -> AppConfig {
     state.config.lock().unwrap().clone()
 }
 
+/// Fails with `IndexOutdated` if there is an index that has to be rebuilt first.
 #[tauri::command]
-pub fn is_db_indexed(state: State<AppState>) -> bool {
-    state.db.lock().unwrap().is_some()
+pub fn is_db_indexed(state: State<AppState>) -> Result<bool, AppError> {
+    if state.index.lock().unwrap().is_some() {
+        return Ok(true);
+    }
+    match state.index_outdated.lock().unwrap().clone() {
+        Some(reason) => Err(AppError::IndexOutdated(reason)),
+        None => Ok(false),
+    }
+}
+
+fn not_indexed_error(state: &AppState) -> AppError {
+    match state.index_outdated.lock().unwrap().clone() {
+        Some(reason) => AppError::IndexOutdated(reason),
+        None => AppError::DatabaseNotIndexed,
+    }
 }
 
 #[tauri::command]
 pub async fn search(
     beatmap_id: i32,
+    filter: Option<SearchFilter>,
     state: State<'_, AppState>,
 ) -> Result<Vec<SearchResultItem>, AppError> {
-    let db_guard = state.db.lock().unwrap();
-    let map_infos_guard = state.map_infos.lock().unwrap();
-    let map_id_to_index_guard = state.map_id_to_index.lock().unwrap();
-
-    let (Some(db), Some(map_infos)) = (db_guard.as_ref(), map_infos_guard.as_ref()) else {
-        return Err(AppError::DatabaseNotIndexed);
+    let index_guard = state.index.lock().unwrap();
+    let Some(index) = index_guard.as_ref() else {
+        return Err(not_indexed_error(&state));
     };
 
-    let Some(&query_map_index) = map_id_to_index_guard.get(&beatmap_id) else {
+    let Some(&query_map_index) = index.map_id_to_index.get(&beatmap_id) else {
         return Err(AppError::MapNotFound(beatmap_id));
     };
 
-    let query_map_info = &map_infos[query_map_index];
+    // the stored vector works even if the .osu file was moved or deleted since indexing
+    let query_vector = &index.vectors[query_map_index];
 
-    let beatmap = core::parser::parse_beatmap_from_file(&query_map_info.path)
-        .map_err(|e| AppError::IoError(e.to_string()))?;
+    let search_results = core::searcher::perform_search(
+        &index.db,
+        &index.map_infos,
+        query_vector,
+        Some(query_map_index),
+        10,
+        &filter.unwrap_or_default(),
+    );
 
+    Ok(to_result_items(search_results))
+}
+
+/// Searches with a `.osu` file that doesn't need to be indexed, `source` is
+/// either a path or the raw file contents (e.g. an unsubmitted WIP diff).
+#[tauri::command]
+pub async fn search_by_osu(
+    source: String,
+    filter: Option<SearchFilter>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchResultItem>, AppError> {
+    let beatmap = core::parser::parse_beatmap_from_source(&source)
+        .map_err(|e| AppError::InvalidBeatmap(e.to_string()))?;
     let query_vector =
         core::features::extract_features(&beatmap).ok_or(AppError::FeatureExtractionFailed)?;
 
-    let search_results =
-        core::searcher::perform_search(db, map_infos, &query_vector, query_map_index, 10);
+    let index_guard = state.index.lock().unwrap();
+    let Some(index) = index_guard.as_ref() else {
+        return Err(not_indexed_error(&state));
+    };
+
+    // WIP maps usually have beatmap_id 0, only skip the query itself if it's a real indexed map
+    let exclude_index = if beatmap.beatmap_id > 0 {
+        index.map_id_to_index.get(&beatmap.beatmap_id).copied()
+    } else {
+        None
+    };
+
+    let search_results = core::searcher::perform_search(
+        &index.db,
+        &index.map_infos,
+        &query_vector,
+        exclude_index,
+        10,
+        &filter.unwrap_or_default(),
+    );
 
-    let response: Vec<SearchResultItem> = search_results
+    Ok(to_result_items(search_results))
+}
+
+fn to_result_items(search_results: Vec<(f32, &MapInfo)>) -> Vec<SearchResultItem> {
+    search_results
         .into_iter()
         .map(|(score, map_info)| {
             let cover_url = format!(
@@ -67,15 +127,26 @@ pub async fn search(
                 cover_url,
             }
         })
-        .collect();
+        .collect()
+}
 
-    Ok(response)
+struct EventProgressSink {
+    app_handle: AppHandle,
+}
+
+impl ProgressSink for EventProgressSink {
+    fn on_progress(&self, update: &ProgressUpdate) {
+        if let Err(e) = self.app_handle.emit("indexing-progress", update) {
+            eprintln!("Failed to emit indexing-progress event: {}", e);
+        }
+    }
 }
 
 #[tauri::command]
 pub async fn index(
     songs_path: String,
     limit: Option<usize>,
+    incremental: Option<bool>,
     state: State<'_, AppState>,
     window: tauri::Window,
 ) -> Result<(), AppError> {
@@ -84,21 +155,43 @@ pub async fn index(
     config_guard.songs_path = Some(songs_path_buf.clone());
     save_config(&config_guard).map_err(|e| AppError::ConfigError(e.to_string()))?;
 
-    *state.indexing_progress.lock().unwrap() = IndexingProgress::default();
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    *state.indexing_cancel_flag.lock().unwrap() = Some(cancel_flag.clone());
 
-    let db_path_arc = state.db_path.clone();
-    let progress_tracker_arc = state.indexing_progress.clone();
+    let db_path = state.db_path.clone();
+    let indexing_lock = state.indexing_lock.clone();
+    let index_generation = state.index_generation.clone();
+    let cancel_flag_arc = state.indexing_cancel_flag.clone();
     let app_handle = window.app_handle().clone();
     tokio::task::spawn_blocking(move || {
-        let db_path = db_path_arc.lock().unwrap();
-        let _ = core::indexer::run_indexing(
+        let sink = EventProgressSink {
+            app_handle: app_handle.clone(),
+        };
+        let indexing_guard = indexing_lock.lock().unwrap();
+        let report = core::indexer::run_indexing(
             Path::new(&songs_path),
             &db_path,
             limit,
-            Some(progress_tracker_arc),
+            incremental.unwrap_or(false),
+            Some(&sink),
+            Some(cancel_flag.clone()),
         );
+        index_generation.fetch_add(1, Ordering::SeqCst);
+        drop(indexing_guard);
+
+        // a newer run may have replaced the flag already
+        let mut cancel_flag_guard = cancel_flag_arc.lock().unwrap();
+        if cancel_flag_guard
+            .as_ref()
+            .is_some_and(|flag| Arc::ptr_eq(flag, &cancel_flag))
+        {
+            *cancel_flag_guard = None;
+        }
+        drop(cancel_flag_guard);
 
-        if let Err(e) = app_handle.emit("index-complete", ()) {
+        crate::watcher::restart(&app_handle, Path::new(&songs_path));
+
+        if let Err(e) = app_handle.emit("index-complete", report) {
             eprintln!("Failed to emit index-complete event: {}", e);
         }
     });
@@ -106,7 +199,22 @@ pub async fn index(
     Ok(())
 }
 
+/// Returns `false` if there was no indexing run to cancel.
+#[tauri::command]
+pub fn cancel_indexing(state: State<AppState>) -> bool {
+    match state.indexing_cancel_flag.lock().unwrap().as_ref() {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The report of the last indexing run, `None` if nothing was indexed yet.
 #[tauri::command]
-pub fn get_indexing_status(state: State<AppState>) -> IndexingProgress {
-    state.indexing_progress.lock().unwrap().clone()
+pub async fn get_indexing_report(
+    state: State<'_, AppState>,
+) -> Result<Option<IndexingReport>, AppError> {
+    Ok(core::indexer::load_report(&state.db_path).ok())
 }
@@ -0,0 +1,273 @@
+use super::model::{Beatmap, HitObject, HitObjectType};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Bump whenever the estimate changes, so stored attributes get recalculated.
+pub const DIFFICULTY_VERSION: u32 = 1;
+
+// constants follow osu!'s difficulty calculator from before the 2021 reworks,
+// close enough to the real star rating to sort maps by difficulty
+const NORMALIZED_RADIUS: f32 = 52.0;
+const MIN_STRAIN_TIME_MS: f32 = 50.0;
+const SECTION_LENGTH_MS: f32 = 400.0;
+const DECAY_WEIGHT: f32 = 0.9;
+const STAR_SCALING: f32 = 0.0675;
+
+const AIM_MULTIPLIER: f32 = 26.25;
+const AIM_DECAY_BASE: f32 = 0.15;
+const AIM_ANGLE_BONUS_BEGIN: f32 = PI / 3.0;
+const AIM_TIMING_THRESHOLD_MS: f32 = 107.0;
+const AIM_ANGLE_BONUS_SCALE: f32 = 90.0;
+
+const SPEED_MULTIPLIER: f32 = 1400.0;
+const SPEED_DECAY_BASE: f32 = 0.3;
+const SPEED_ANGLE_BONUS_BEGIN: f32 = 5.0 * PI / 6.0;
+const SINGLE_SPACING_THRESHOLD: f32 = 125.0;
+const MIN_SPEED_BONUS_MS: f32 = 75.0; // ~200 BPM 1/4
+const MAX_SPEED_BONUS_MS: f32 = 45.0; // ~330 BPM 1/4
+const SPEED_BALANCING_FACTOR: f32 = 40.0;
+
+/// Locally estimated difficulty, osu!standard only.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct DifficultyAttributes {
+    pub star_rating: f32,
+    pub aim: f32,
+    pub speed: f32,
+    pub max_combo: u32,
+}
+
+/// What the strain skills need to know about an object and the one before it.
+struct DifficultyObject {
+    start_time: f32,
+    /// ms since the previous object, at least `MIN_STRAIN_TIME_MS`
+    strain_time: f32,
+    /// from the previous object's (lazy) end to this head, in normalized pixels
+    jump_distance: f32,
+    /// how far the cursor had to follow the previous slider, in normalized pixels
+    travel_distance: f32,
+    /// angle at the previous object between coming in and going here, π = straight on
+    angle: Option<f32>,
+}
+
+pub fn calculate(beatmap: &Beatmap) -> DifficultyAttributes {
+    let max_combo = beatmap.hit_objects.iter().map(combo_of).sum();
+    let objects = difficulty_objects(beatmap);
+    if objects.is_empty() {
+        return DifficultyAttributes {
+            max_combo,
+            ..Default::default()
+        };
+    }
+
+    let aim_strain = strain_difficulty(&objects, AIM_MULTIPLIER, AIM_DECAY_BASE, |i| {
+        aim_value(&objects, i)
+    });
+    let speed_strain = strain_difficulty(&objects, SPEED_MULTIPLIER, SPEED_DECAY_BASE, |i| {
+        speed_value(&objects[i])
+    });
+    let aim = aim_strain.sqrt() * STAR_SCALING;
+    let speed = speed_strain.sqrt() * STAR_SCALING;
+
+    DifficultyAttributes {
+        star_rating: aim + speed + (aim - speed).abs() / 2.0,
+        aim,
+        speed,
+        max_combo,
+    }
+}
+
+/// Head, ticks, repeats and tail for sliders, 1 for everything else.
+fn combo_of(obj: &HitObject) -> u32 {
+    match &obj.slider {
+        Some(slider) => 1 + slider.ticks.len() as u32 + slider.span_count,
+        None => 1,
+    }
+}
+
+fn difficulty_objects(beatmap: &Beatmap) -> Vec<DifficultyObject> {
+    let radius = beatmap.difficulty.circle_radius();
+    let mut scale = NORMALIZED_RADIUS / radius;
+    // small circles are harder to hit than their size alone suggests
+    if radius < 30.0 {
+        scale *= 1.0 + (30.0 - radius).min(5.0) / 50.0;
+    }
+
+    let lazy: Vec<((f32, f32), f32)> = beatmap
+        .hit_objects
+        .iter()
+        .map(|obj| lazy_slider_travel(obj, radius))
+        .collect();
+    let scaled_distance =
+        |a: (f32, f32), b: (f32, f32)| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() * scale;
+
+    let hit_objects = &beatmap.hit_objects;
+    (1..hit_objects.len())
+        .map(|i| {
+            let (current, last) = (&hit_objects[i], &hit_objects[i - 1]);
+            let is_spinner = |obj: &HitObject| obj.obj_type == HitObjectType::Spinner;
+            let (last_end, last_travel) = lazy[i - 1];
+
+            let jump_distance = if is_spinner(current) || is_spinner(last) {
+                0.0
+            } else {
+                scaled_distance(last_end, (current.x, current.y))
+            };
+            let angle = (i >= 2 && !is_spinner(last)).then(|| {
+                let (last_last_end, _) = lazy[i - 2];
+                let v1 = (last_last_end.0 - last.x, last_last_end.1 - last.y);
+                let v2 = (current.x - last_end.0, current.y - last_end.1);
+                let dot = v1.0 * v2.0 + v1.1 * v2.1;
+                let det = v1.0 * v2.1 - v1.1 * v2.0;
+                det.atan2(dot).abs()
+            });
+
+            DifficultyObject {
+                start_time: current.start_time,
+                strain_time: (current.start_time - last.start_time).max(MIN_STRAIN_TIME_MS),
+                jump_distance,
+                travel_distance: last_travel * scale,
+                angle,
+            }
+        })
+        .collect()
+}
+
+/// Where the cursor ends up and how far it moved when it only follows the
+/// slider ball as much as the follow circle requires.
+fn lazy_slider_travel(obj: &HitObject, radius: f32) -> ((f32, f32), f32) {
+    let Some(slider) = &obj.slider else {
+        return ((obj.x, obj.y), 0.0);
+    };
+    let follow_radius = radius * 3.0;
+
+    // ticks, repeats and the end, in the order the ball reaches them
+    let span_duration = (obj.end_time - obj.start_time) / slider.span_count.max(1) as f32;
+    let mut checkpoints: Vec<(f32, (f32, f32))> = slider
+        .ticks
+        .iter()
+        .map(|tick| (tick.time, tick.position))
+        .collect();
+    for span in 1..=slider.span_count {
+        checkpoints.push((
+            obj.start_time + span_duration * span as f32,
+            slider.path.end_position(span),
+        ));
+    }
+    checkpoints.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut cursor = (obj.x, obj.y);
+    let mut travel = 0.0;
+    for (_, position) in checkpoints {
+        let diff = (position.0 - cursor.0, position.1 - cursor.1);
+        let distance = (diff.0 * diff.0 + diff.1 * diff.1).sqrt();
+        if distance > follow_radius {
+            let moved = distance - follow_radius;
+            cursor.0 += diff.0 / distance * moved;
+            cursor.1 += diff.1 / distance * moved;
+            travel += moved;
+        }
+    }
+    (cursor, travel)
+}
+
+fn aim_value(objects: &[DifficultyObject], i: usize) -> f32 {
+    let current = &objects[i];
+    let mut angle_bonus = 0.0;
+    if let (Some(angle), Some(previous)) = (current.angle, i.checked_sub(1).map(|p| &objects[p])) {
+        if angle > AIM_ANGLE_BONUS_BEGIN {
+            let bonus = ((previous.jump_distance - AIM_ANGLE_BONUS_SCALE).max(0.0)
+                * (angle - AIM_ANGLE_BONUS_BEGIN).sin().powi(2)
+                * (current.jump_distance - AIM_ANGLE_BONUS_SCALE).max(0.0))
+            .sqrt();
+            angle_bonus = 1.5 * diminish(bonus) / previous.strain_time.max(AIM_TIMING_THRESHOLD_MS);
+        }
+    }
+
+    let jump = diminish(current.jump_distance);
+    let travel = diminish(current.travel_distance);
+    let distance = jump + travel + (jump * travel).sqrt();
+    (angle_bonus + distance / current.strain_time.max(AIM_TIMING_THRESHOLD_MS))
+        .max(distance / current.strain_time)
+}
+
+fn speed_value(current: &DifficultyObject) -> f32 {
+    let distance = (current.travel_distance + current.jump_distance).min(SINGLE_SPACING_THRESHOLD);
+    let delta_time = current.strain_time.max(MAX_SPEED_BONUS_MS);
+
+    let speed_bonus = if delta_time < MIN_SPEED_BONUS_MS {
+        1.0 + ((MIN_SPEED_BONUS_MS - delta_time) / SPEED_BALANCING_FACTOR).powi(2)
+    } else {
+        1.0
+    };
+
+    let mut angle_bonus = 1.0;
+    if let Some(angle) = current
+        .angle
+        .filter(|&angle| angle < SPEED_ANGLE_BONUS_BEGIN)
+    {
+        angle_bonus = 1.0 + (1.5 * (SPEED_ANGLE_BONUS_BEGIN - angle)).sin().powi(2) / 3.57;
+        if angle < PI / 2.0 {
+            angle_bonus = 1.28;
+            // stacked or overlapping back-and-forths aren't harder to tap
+            if distance < 90.0 {
+                let closeness = ((90.0 - distance) / 10.0).min(1.0);
+                angle_bonus += (1.0 - angle_bonus)
+                    * if angle < PI / 4.0 {
+                        closeness
+                    } else {
+                        closeness * ((PI / 2.0 - angle) / (PI / 4.0)).sin()
+                    };
+            }
+        }
+    }
+
+    (1.0 + (speed_bonus - 1.0) * 0.75)
+        * angle_bonus
+        * (0.95 + speed_bonus * (distance / SINGLE_SPACING_THRESHOLD).powf(3.5))
+        / current.strain_time
+}
+
+fn diminish(value: f32) -> f32 {
+    value.powf(0.99)
+}
+
+/// Sum of the per-section strain peaks, the hardest sections weighted the most.
+fn strain_difficulty(
+    objects: &[DifficultyObject],
+    multiplier: f32,
+    decay_base: f32,
+    strain_value: impl Fn(usize) -> f32,
+) -> f32 {
+    let decay = |ms: f32| decay_base.powf(ms / 1000.0);
+
+    let mut peaks = Vec::new();
+    let mut section_end = (objects[0].start_time / SECTION_LENGTH_MS).ceil() * SECTION_LENGTH_MS;
+    let mut section_peak: f32 = 0.0;
+    let mut current_strain = 0.0;
+    let mut previous_time = objects[0].start_time;
+    for (i, obj) in objects.iter().enumerate() {
+        while obj.start_time > section_end {
+            peaks.push(section_peak);
+            // the strain keeps decaying into the next section
+            section_peak = current_strain * decay(section_end - previous_time);
+            section_end += SECTION_LENGTH_MS;
+        }
+
+        current_strain *= decay(obj.start_time - previous_time);
+        current_strain += strain_value(i) * multiplier;
+        section_peak = section_peak.max(current_strain);
+        previous_time = obj.start_time;
+    }
+    peaks.push(section_peak);
+
+    peaks.sort_by(|a, b| b.total_cmp(a));
+    let mut weight = 1.0;
+    peaks
+        .iter()
+        .map(|peak| {
+            let weighted = peak * weight;
+            weight *= DECAY_WEIGHT;
+            weighted
+        })
+        .sum()
+}
@@ -0,0 +1,79 @@
+use super::movement_distance;
+use crate::core::model::{Difficulty, HitObject, HitObjectType};
+use crate::core::timing::Timing;
+use std::collections::HashSet;
+
+// ratios are clamped to this, big jumps aren't distance snapped anyway
+const MAX_DS_RATIO: f32 = 4.0;
+// the editor steps distance snap by 0.1x
+const DS_STEP: f32 = 0.1;
+const MAX_DISTINCT_VALUES: f32 = 16.0;
+// pauses longer than this aren't spaced by time anymore
+const MAX_DS_GAP_BEATS: f32 = 2.0;
+// in osu!pixels, stacks have no spacing to measure
+const MIN_DS_DISTANCE: f32 = 1.0;
+
+/// How strictly objects are spaced by time, all in 0..1.
+#[derive(Debug, Default)]
+pub struct DistanceSnapStats {
+    /// average DS ratio, normalized by `MAX_DS_RATIO`
+    pub mean: f32,
+    /// variance of the DS ratio, normalized by the largest one possible
+    pub variance: f32,
+    /// how many `DS_STEP` values the map uses, normalized by `MAX_DISTINCT_VALUES`
+    pub distinct_values: f32,
+}
+
+/// DS ratio of every move: the distance from the previous object's end divided by
+/// the distance the slider velocity covers in that time, like the editor's distance snap.
+pub fn analyze(
+    hit_objects: &[HitObject],
+    timing: &Timing,
+    difficulty: &Difficulty,
+) -> DistanceSnapStats {
+    let ratios: Vec<f32> = hit_objects
+        .windows(2)
+        .filter_map(|pair| ds_ratio(&pair[0], &pair[1], timing, difficulty))
+        .collect();
+    if ratios.is_empty() {
+        return DistanceSnapStats::default();
+    }
+
+    let count = ratios.len() as f32;
+    let mean = ratios.iter().sum::<f32>() / count;
+    let variance = ratios.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / count;
+    let distinct: HashSet<i32> = ratios
+        .iter()
+        .map(|r| (r / DS_STEP).round() as i32)
+        .collect();
+
+    DistanceSnapStats {
+        mean: mean / MAX_DS_RATIO,
+        // values within 0..max can't spread further than (max / 2)²
+        variance: variance / (MAX_DS_RATIO / 2.0).powi(2),
+        distinct_values: (distinct.len() as f32 / MAX_DISTINCT_VALUES).min(1.0),
+    }
+}
+
+fn ds_ratio(
+    from: &HitObject,
+    to: &HitObject,
+    timing: &Timing,
+    difficulty: &Difficulty,
+) -> Option<f32> {
+    let is_spinner = |obj: &HitObject| obj.obj_type == HitObjectType::Spinner;
+    if is_spinner(from) || is_spinner(to) {
+        return None;
+    }
+    let gap = to.start_time - from.end_time;
+    let distance = movement_distance(from, to);
+    if gap <= 0.0
+        || gap > MAX_DS_GAP_BEATS * timing.beat_length_at(to.start_time)
+        || distance < MIN_DS_DISTANCE
+    {
+        return None;
+    }
+
+    let (velocity, _) = timing.slider_velocity_at(difficulty, to.start_time);
+    Some((distance / (gap * velocity)).min(MAX_DS_RATIO))
+}
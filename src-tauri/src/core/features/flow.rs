@@ -0,0 +1,150 @@
+use super::{tail_position, turn_between};
+use crate::core::model::{HitObject, HitObjectType};
+
+// in circle radii, shorter moves (stacks, overlapping streams) have no real direction
+const MIN_MOVE_RADII: f32 = 0.5;
+// a pause this long ends the flow
+const MAX_FLOW_GAP_MS: f32 = 1000.0;
+// turns this close to straight on (or straight back) don't go either way
+const STRAIGHT_MAX_TURN_DEG: f32 = 5.0;
+// rotating the same way without snapping back, for at least this many turns
+const CIRCULAR_MAX_TURN_DEG: f32 = 120.0;
+const CIRCULAR_MIN_TURNS: usize = 3;
+
+/// How the cursor moves from object to object. Every turn is weighted by the
+/// size of the jumps around it, so streams don't drown out the jumps.
+#[derive(Debug, Default)]
+pub struct FlowStats {
+    /// -1..1, positive when the cursor mostly turns clockwise (on screen)
+    pub turn_direction: f32,
+    /// 0..1, how often the cursor turns the other way than the turn before
+    pub direction_change_rate: f32,
+    /// 0..1, share of turns that keep rotating the same way (circles, spirals)
+    pub circular_flow: f32,
+    /// 0..1, acute turns (snapping back, more than 90°) vs obtuse ones (flowing on)
+    pub acute_ratio: f32,
+}
+
+struct Turn {
+    /// degrees, positive = clockwise
+    angle: f32,
+    weight: f32,
+}
+
+impl Turn {
+    fn direction(&self) -> i8 {
+        if !(STRAIGHT_MAX_TURN_DEG..=180.0 - STRAIGHT_MAX_TURN_DEG).contains(&self.angle.abs()) {
+            0
+        } else if self.angle > 0.0 {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+pub fn analyze(hit_objects: &[HitObject], circle_radius: f32) -> FlowStats {
+    let sections = turn_sections(hit_objects, MIN_MOVE_RADII * circle_radius);
+    let total_weight: f32 = sections.iter().flatten().map(|turn| turn.weight).sum();
+    if total_weight <= 0.0 {
+        return FlowStats::default();
+    }
+
+    let mut signed_weight = 0.0;
+    let mut acute_weight = 0.0;
+    let mut changed_weight = 0.0;
+    let mut compared_weight = 0.0;
+    let mut circular_weight = 0.0;
+    for section in &sections {
+        for turn in section {
+            signed_weight += turn.direction() as f32 * turn.weight;
+            if turn.angle.abs() > 90.0 {
+                acute_weight += turn.weight;
+            }
+        }
+
+        let mut previous_direction = 0;
+        for turn in section {
+            let direction = turn.direction();
+            if direction == 0 {
+                continue;
+            }
+            if previous_direction != 0 {
+                compared_weight += turn.weight;
+                if direction != previous_direction {
+                    changed_weight += turn.weight;
+                }
+            }
+            previous_direction = direction;
+        }
+
+        let mut run_direction = 0;
+        let mut run_turns = 0;
+        let mut run_weight = 0.0;
+        for turn in section {
+            let direction = turn.direction();
+            let rotates = direction != 0 && turn.angle.abs() <= CIRCULAR_MAX_TURN_DEG;
+            if !rotates || direction != run_direction {
+                if run_turns >= CIRCULAR_MIN_TURNS {
+                    circular_weight += run_weight;
+                }
+                run_direction = if rotates { direction } else { 0 };
+                run_turns = 0;
+                run_weight = 0.0;
+            }
+            if rotates {
+                run_turns += 1;
+                run_weight += turn.weight;
+            }
+        }
+        if run_turns >= CIRCULAR_MIN_TURNS {
+            circular_weight += run_weight;
+        }
+    }
+
+    FlowStats {
+        turn_direction: signed_weight / total_weight,
+        direction_change_rate: if compared_weight > 0.0 {
+            changed_weight / compared_weight
+        } else {
+            0.0
+        },
+        circular_flow: circular_weight / total_weight,
+        acute_ratio: acute_weight / total_weight,
+    }
+}
+
+/// Turns between consecutive moves, split wherever the flow is interrupted
+/// (breaks, spinners, stacks).
+fn turn_sections(hit_objects: &[HitObject], min_move: f32) -> Vec<Vec<Turn>> {
+    let mut sections = vec![Vec::new()];
+    let mut previous_move: Option<(f32, f32)> = None;
+    for pair in hit_objects.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        // the cursor leaves from the slider end, not its head
+        let (x, y) = tail_position(from);
+        let movement = (to.x - x, to.y - y);
+        let length = movement.0.hypot(movement.1);
+
+        let interrupted = to.start_time - from.end_time > MAX_FLOW_GAP_MS
+            || from.obj_type == HitObjectType::Spinner
+            || to.obj_type == HitObjectType::Spinner
+            || length < min_move;
+        if interrupted {
+            previous_move = None;
+            if sections.last().is_some_and(|section| !section.is_empty()) {
+                sections.push(Vec::new());
+            }
+            continue;
+        }
+
+        if let Some(previous) = previous_move {
+            sections.last_mut().unwrap().push(Turn {
+                angle: turn_between(previous, movement),
+                weight: (previous.0.hypot(previous.1) + length) / 2.0,
+            });
+        }
+        previous_move = Some(movement);
+    }
+    sections
+}
@@ -0,0 +1,56 @@
+use super::{get_angle, movement_distance};
+use crate::core::model::{HitObject, HitObjectType};
+
+pub const BIN_COUNT: usize = 8;
+
+// values below the first edge go into the first bin, above the last into the last one
+// in circle radii, 2 = touching circles
+const DISTANCE_EDGES_RADII: [f32; BIN_COUNT - 1] = [0.5, 1.0, 2.0, 3.0, 4.0, 6.0, 8.0];
+// turning angle at the middle object, 0° = back and forth, 180° = straight line
+const ANGLE_EDGES_DEG: [f32; BIN_COUNT - 1] = [22.5, 45.0, 67.5, 90.0, 112.5, 135.0, 157.5];
+// from the end of the previous object, 1/4 streams at 170-250 BPM land in the second bin
+const TIME_GAP_EDGES_MS: [f32; BIN_COUNT - 1] = [60.0, 90.0, 125.0, 170.0, 250.0, 350.0, 500.0];
+
+/// Normalized histograms of how the cursor moves between objects, each sums to 1
+/// (or is all zeros when there's nothing to measure).
+#[derive(Debug, Default)]
+pub struct MovementHistograms {
+    pub distance: [f32; BIN_COUNT],
+    pub angle: [f32; BIN_COUNT],
+    pub time_gap: [f32; BIN_COUNT],
+}
+
+pub fn analyze(hit_objects: &[HitObject], circle_radius: f32) -> MovementHistograms {
+    // spinners sit in the middle of the playfield, their position means nothing
+    let is_spinner = |obj: &HitObject| obj.obj_type == HitObjectType::Spinner;
+
+    let distances = hit_objects
+        .windows(2)
+        .filter(|pair| !pair.iter().any(is_spinner))
+        .map(|pair| movement_distance(&pair[0], &pair[1]) / circle_radius);
+    let angles = hit_objects
+        .windows(3)
+        .filter(|triple| !triple.iter().any(is_spinner))
+        .filter_map(|triple| get_angle(&triple[0], &triple[1], &triple[2]));
+    let time_gaps = hit_objects
+        .windows(2)
+        .map(|pair| pair[1].start_time - pair[0].end_time);
+
+    MovementHistograms {
+        distance: histogram(distances, &DISTANCE_EDGES_RADII),
+        angle: histogram(angles, &ANGLE_EDGES_DEG),
+        time_gap: histogram(time_gaps, &TIME_GAP_EDGES_MS),
+    }
+}
+
+fn histogram(values: impl Iterator<Item = f32>, edges: &[f32; BIN_COUNT - 1]) -> [f32; BIN_COUNT] {
+    let mut counts = [0; BIN_COUNT];
+    for value in values {
+        counts[edges.partition_point(|&edge| edge <= value)] += 1;
+    }
+    let total = counts.iter().sum::<usize>();
+    if total == 0 {
+        return [0.0; BIN_COUNT];
+    }
+    counts.map(|count| count as f32 / total as f32)
+}
@@ -0,0 +1,389 @@
+mod distance_snap;
+mod flow;
+mod histograms;
+mod rhythm;
+pub mod shapes;
+mod sliders;
+mod streams;
+mod symmetry;
+
+use super::model::{Beatmap, HitObject, HitObjectType};
+use seli_vector_db::Vector;
+
+pub const FEATURE_COUNT: usize = 68;
+/// Bump whenever a feature is added, removed or computed differently (including
+/// changes to parsing, slider paths or timing), so indexes built by older
+/// versions get rebuilt.
+pub const FEATURE_SCHEMA_VERSION: u32 = 16;
+
+pub const WEIGHTS: [f32; FEATURE_COUNT] = [
+    1.5, // reading_overlap_ratio, tech
+    1.0, // visible_density, reading
+    0.8, // grid_adherence
+    0.7, // circle_ratio
+    1.2, // sharp_slider_ratio, tech
+    1.3, // intensity (difficulty)
+    1.0, // slider_mean_curvature
+    0.6, // slider_max_curvature
+    0.8, // anchors_per_slider
+    0.8, // slider_curve_length_ratio
+    1.2, // art_slider_ratio, slider art/blankets
+    0.8, // slider_velocity_variation
+    1.0, // horizontal_symmetry
+    1.0, // vertical_symmetry
+    0.8, // point_symmetry
+    0.6, // snap_1_1
+    0.6, // snap_1_2
+    0.6, // snap_1_3
+    0.6, // snap_1_4
+    0.6, // snap_1_6
+    0.6, // snap_1_8
+    1.2, // triplet_ratio
+    0.8, // rhythm_change_entropy
+    1.0, // burst_ratio
+    1.0, // short_stream_ratio
+    1.0, // long_stream_ratio
+    1.0, // deathstream_ratio
+    0.8, // longest_stream
+    1.0, // distance_under_0.5_radii
+    1.0, // distance_0.5_1_radii
+    1.0, // distance_1_2_radii
+    1.0, // distance_2_3_radii
+    1.0, // distance_3_4_radii
+    1.0, // distance_4_6_radii
+    1.0, // distance_6_8_radii
+    1.0, // distance_over_8_radii
+    1.0, // angle_0_22
+    1.0, // angle_22_45
+    1.0, // angle_45_67
+    1.0, // angle_67_90
+    1.0, // angle_90_112
+    1.0, // angle_112_135
+    1.0, // angle_135_157
+    1.0, // angle_157_180
+    0.8, // gap_under_60ms
+    0.8, // gap_60_90ms
+    0.8, // gap_90_125ms
+    0.8, // gap_125_170ms
+    0.8, // gap_170_250ms
+    0.8, // gap_250_350ms
+    0.8, // gap_350_500ms
+    0.8, // gap_over_500ms
+    1.0, // stream_ratio
+    1.0, // spaced_stream_ratio
+    0.8, // triangle_ratio
+    0.8, // square_ratio
+    0.8, // pentagon_ratio
+    0.8, // hexagon_ratio
+    1.0, // back_and_forth_ratio
+    0.8, // zig_zag_ratio
+    0.8, // linear_stack_ratio
+    0.6, // turn_direction
+    1.0, // direction_change_rate
+    1.2, // circular_flow
+    1.2, // acute_turn_ratio, snap vs flow aim
+    0.8, // ds_mean
+    1.2, // ds_variance, strict spacing vs freestyle
+    1.0, // ds_distinct_values
+];
+
+/// Identifies the vectors `extract_features` produces. Stored with the index,
+/// any mismatch means the old vectors can't be compared with new ones.
+pub fn schema_fingerprint() -> String {
+    let mut bytes = Vec::new();
+    bytes.extend(FEATURE_SCHEMA_VERSION.to_le_bytes());
+    bytes.extend((FEATURE_COUNT as u32).to_le_bytes());
+    for weight in WEIGHTS {
+        bytes.extend(weight.to_le_bytes());
+    }
+    format!("{:x}", md5::compute(bytes))
+}
+
+pub fn extract_features(beatmap: &Beatmap) -> Option<Vector> {
+    if beatmap.hit_objects.is_empty() {
+        return None;
+    }
+
+    let total_objects = beatmap.hit_objects.len() as f32;
+    if total_objects < 3.0 {
+        return Some(vec![0.0; FEATURE_COUNT]);
+    }
+
+    let mut reading_overlap_count = 0;
+    let mut visible_object_total = 0;
+    let mut grid_snap_count = 0;
+
+    let mut stream_pattern_count = 0;
+    let mut spaced_stream_pattern_count = 0;
+    let mut flow_pattern_total = 0;
+    let mut total_time_intervals = 0.0;
+
+    const GRID_SIZE: f32 = 8.0;
+    const GRID_TOLERANCE: f32 = 1.0;
+
+    // >240 BPM 1/4 notes
+    const STREAM_MAX_TIME_MS: f32 = 125.0;
+
+    // sliders/spinners longer than this can't hide an object that fades in
+    const MAX_LOOKBACK_MS: f32 = 5000.0;
+
+    // in circle radii, so a cs7 stream isn't judged by cs4 pixel distances (100/200 px at cs4)
+    const DENSE_STREAM_MAX_RADII: f32 = 2.75;
+    const SPACED_STREAM_MAX_RADII: f32 = 5.5;
+
+    let circle_radius = beatmap.difficulty.circle_radius();
+    let preempt = beatmap.difficulty.preempt_ms();
+    let dense_stream_max_dist = DENSE_STREAM_MAX_RADII * circle_radius;
+    let spaced_stream_max_dist = SPACED_STREAM_MAX_RADII * circle_radius;
+
+    for i in 0..beatmap.hit_objects.len() {
+        let current_obj = &beatmap.hit_objects[i];
+
+        // grid check
+        if is_grid_snapped(current_obj, GRID_SIZE, GRID_TOLERANCE) {
+            grid_snap_count += 1;
+        }
+
+        // flow check
+        if i > 0 {
+            flow_pattern_total += 1;
+            let prev_obj = &beatmap.hit_objects[i - 1];
+            // the cursor leaves from the slider end, not its head
+            let dist = movement_distance(prev_obj, current_obj);
+            let time_diff = current_obj.start_time - prev_obj.end_time;
+            total_time_intervals += time_diff;
+
+            // spacing only tells streams apart when the objects are stream timed
+            if time_diff > 0.0 && time_diff < STREAM_MAX_TIME_MS {
+                if dist < dense_stream_max_dist {
+                    stream_pattern_count += 1;
+                } else if dist < spaced_stream_max_dist {
+                    // spaced stream / alt
+                    spaced_stream_pattern_count += 1;
+                }
+            }
+        }
+
+        // visual overlap check, against everything still on screen when this object fades in
+        let appear_time = current_obj.start_time - preempt;
+        if current_obj.obj_type != HitObjectType::Spinner {
+            let overlaps_visible = beatmap.hit_objects[..i]
+                .iter()
+                .rev()
+                .take_while(|past_obj| past_obj.start_time >= appear_time - MAX_LOOKBACK_MS)
+                .any(|past_obj| {
+                    past_obj.obj_type != HitObjectType::Spinner
+                        && past_obj.end_time >= appear_time
+                        && euclidean_distance(current_obj, past_obj) < circle_radius
+                });
+            if overlaps_visible {
+                reading_overlap_count += 1;
+            }
+        }
+
+        // objects already faded in when this one has to be hit (ar10 cut streams vs ar8 stacks)
+        visible_object_total += beatmap.hit_objects[i + 1..]
+            .iter()
+            .take_while(|next_obj| next_obj.start_time - preempt <= current_obj.start_time)
+            .count();
+    }
+
+    // analyze obj types
+    let circle_ratio = beatmap
+        .hit_objects
+        .iter()
+        .filter(|o| o.obj_type == HitObjectType::Circle)
+        .count() as f32
+        / total_objects;
+    let sliders: Vec<&HitObject> = beatmap
+        .hit_objects
+        .iter()
+        .filter(|o| o.obj_type == HitObjectType::Slider)
+        .collect();
+    let slider_count = sliders.len();
+
+    // look for red points
+    let sharp_slider_ratio = if slider_count > 0 {
+        let sharp_sliders = sliders
+            .iter()
+            .filter(|s| {
+                if let Some(points) = &s.curve_points {
+                    for i in 0..points.len().saturating_sub(1) {
+                        let is_red_anchor = points[i] == points[i + 1];
+                        if is_red_anchor {
+                            return true;
+                        }
+                    }
+                }
+                false
+            })
+            .count();
+        sharp_sliders as f32 / slider_count as f32
+    } else {
+        0.0
+    };
+
+    let slider_shapes = sliders::analyze(&sliders);
+    let symmetry = symmetry::analyze(&beatmap.hit_objects, circle_radius);
+    let rhythm = rhythm::analyze(&beatmap.hit_objects, &beatmap.timing);
+    let streams = streams::analyze(&beatmap.hit_objects, STREAM_MAX_TIME_MS);
+    let movement = histograms::analyze(&beatmap.hit_objects, circle_radius);
+    let shape_events = shapes::detect(&beatmap.hit_objects, circle_radius);
+    let flow = flow::analyze(&beatmap.hit_objects, circle_radius);
+    let distance_snap =
+        distance_snap::analyze(&beatmap.hit_objects, &beatmap.timing, &beatmap.difficulty);
+
+    let mut features = Vec::with_capacity(FEATURE_COUNT);
+
+    // Feature 1: Reading Overlap Ratio (reading difficulty)
+    features.push(if total_objects > 0.0 {
+        reading_overlap_count as f32 / total_objects
+    } else {
+        0.0
+    });
+
+    // Feature 2: Visible Object Density (how much is on screen at once)
+    const MAX_VISIBLE_OBJECTS: f32 = 10.0;
+    features.push((visible_object_total as f32 / total_objects / MAX_VISIBLE_OBJECTS).min(1.0));
+
+    // Feature 3: Grid Adherence Ratio
+    features.push(if total_objects > 0.0 {
+        grid_snap_count as f32 / total_objects
+    } else {
+        0.0
+    });
+
+    // Feature 4: Circle Ratio (jumps vs sliders)
+    features.push(circle_ratio);
+
+    // Feature 5: Sharp Slider Ratio (slider technicality)
+    features.push(sharp_slider_ratio);
+
+    // Feature 6: Overall Intensity (normalized average interval)
+    const MIN_INTERVAL: f32 = 50.0; // 1/16 notes 300 BPM
+    const MAX_INTERVAL: f32 = 500.0; // 1/4 notes 120 BPM
+    let avg_interval_ms = if flow_pattern_total > 0 {
+        total_time_intervals / flow_pattern_total as f32
+    } else {
+        0.0
+    };
+    let normalized_interval = (avg_interval_ms - MIN_INTERVAL) / (MAX_INTERVAL - MIN_INTERVAL);
+    features.push(1.0 - normalized_interval.clamp(0.0, 1.0));
+
+    // Feature 7: Slider Shapes (curvature, anchors, art)
+    features.push(slider_shapes.mean_curvature);
+    features.push(slider_shapes.max_curvature);
+    features.push(slider_shapes.anchors_per_slider);
+    features.push(slider_shapes.curve_length_ratio);
+    features.push(slider_shapes.art_slider_ratio);
+    features.push(slider_shapes.velocity_variation);
+
+    // Feature 8: Symmetry (mirrored patterns across the playfield axes)
+    features.push(symmetry.horizontal);
+    features.push(symmetry.vertical);
+    features.push(symmetry.point);
+
+    // Feature 9: Rhythm (snap divisors, triplets, how often the rhythm changes)
+    features.extend(rhythm.snap_histogram);
+    features.push(rhythm.triplet_ratio);
+    features.push(rhythm.change_entropy);
+
+    // Feature 10: Stream Lengths (bursts vs stamina)
+    features.push(streams.burst_ratio);
+    features.push(streams.short_stream_ratio);
+    features.push(streams.long_stream_ratio);
+    features.push(streams.deathstream_ratio);
+    features.push(streams.longest_stream);
+
+    // Feature 11: Movement Histograms (jump distance, turning angle, time gap, stream spacing)
+    features.extend(movement.distance);
+    features.extend(movement.angle);
+    features.extend(movement.time_gap);
+    features.push(if flow_pattern_total > 0 {
+        stream_pattern_count as f32 / flow_pattern_total as f32
+    } else {
+        0.0
+    });
+    features.push(if flow_pattern_total > 0 {
+        spaced_stream_pattern_count as f32 / flow_pattern_total as f32
+    } else {
+        0.0
+    });
+
+    // Feature 12: Shapes (polygons, back-and-forth, zig-zags, linear stacks)
+    features.extend(shapes::frequencies(
+        &shape_events,
+        beatmap.hit_objects.len(),
+    ));
+
+    // Feature 13: Flow (turn direction, circular flow vs snapping back)
+    features.push(flow.turn_direction);
+    features.push(flow.direction_change_rate);
+    features.push(flow.circular_flow);
+    features.push(flow.acute_ratio);
+
+    // Feature 14: Distance Snap (spacing per unit of time, strict DS vs freestyle)
+    features.push(distance_snap.mean);
+    features.push(distance_snap.variance);
+    features.push(distance_snap.distinct_values);
+
+    for i in 0..features.len() {
+        features[i] *= WEIGHTS[i];
+    }
+
+    Some(features)
+}
+
+/// Angle at `p2` between the movement coming from `p1` and the one going to `p3`.
+fn get_angle(p1: &HitObject, p2: &HitObject, p3: &HitObject) -> Option<f32> {
+    let (in_x, in_y) = tail_position(p1);
+    let (out_x, out_y) = tail_position(p2);
+    let v1_x = in_x - p2.x;
+    let v1_y = in_y - p2.y;
+    let v2_x = p3.x - out_x;
+    let v2_y = p3.y - out_y;
+
+    let len1_sq = v1_x.powi(2) + v1_y.powi(2);
+    let len2_sq = v2_x.powi(2) + v2_y.powi(2);
+
+    if len1_sq > 0.0 && len2_sq > 0.0 {
+        let dot_product = v1_x * v2_x + v1_y * v2_y;
+        let cross_product = v1_x * v2_y - v1_y * v2_x;
+        let angle_rad = cross_product.atan2(dot_product);
+        Some(angle_rad.abs().to_degrees())
+    } else {
+        None
+    }
+}
+
+/// Change of direction between two movements in degrees, 0 = straight on,
+/// ±180 = straight back.
+fn turn_between(move_in: (f32, f32), move_out: (f32, f32)) -> f32 {
+    let cross = move_in.0 * move_out.1 - move_in.1 * move_out.0;
+    let dot = move_in.0 * move_out.0 + move_in.1 * move_out.1;
+    cross.atan2(dot).to_degrees()
+}
+
+/// Where the cursor is when `obj` is done: the slider end, or the object itself.
+fn tail_position(obj: &HitObject) -> (f32, f32) {
+    obj.slider
+        .as_ref()
+        .map_or((obj.x, obj.y), |slider| slider.end_position)
+}
+
+fn movement_distance(from: &HitObject, to: &HitObject) -> f32 {
+    let (x, y) = tail_position(from);
+    ((to.x - x).powi(2) + (to.y - y).powi(2)).sqrt()
+}
+
+fn euclidean_distance(p1: &HitObject, p2: &HitObject) -> f32 {
+    ((p1.x - p2.x).powi(2) + (p1.y - p2.y).powi(2)).sqrt()
+}
+
+fn is_grid_snapped(obj: &HitObject, grid_size: f32, tolerance: f32) -> bool {
+    let x_rem = obj.x % grid_size;
+    let y_rem = obj.y % grid_size;
+
+    (x_rem.abs() < tolerance || (grid_size - x_rem).abs() < tolerance)
+        && (y_rem.abs() < tolerance || (grid_size - y_rem).abs() < tolerance)
+}
@@ -0,0 +1,104 @@
+use crate::core::model::HitObject;
+use crate::core::timing::Timing;
+use std::collections::HashMap;
+
+/// Beat divisors objects get snapped to, the order of `RhythmStats::snap_histogram`.
+pub const SNAP_DIVISORS: [u32; 6] = [1, 2, 3, 4, 6, 8];
+
+// osu! stores whole milliseconds, so snapped objects can be off by a rounding step
+const SNAP_TOLERANCE_MS: f32 = 2.0;
+// 24 ticks per beat can express every divisor above
+const TICKS_PER_BEAT: f32 = 24.0;
+// gaps longer than this are breaks, not rhythm
+const MAX_RHYTHM_GAP_BEATS: f32 = 2.0;
+const MAX_ENTROPY_BITS: f32 = 4.0;
+
+/// Rhythm features, all in 0..1.
+#[derive(Debug, Default)]
+pub struct RhythmStats {
+    /// share of objects on each of `SNAP_DIVISORS` (the coarsest one that fits),
+    /// unsnapped objects count in none of them
+    pub snap_histogram: [f32; SNAP_DIVISORS.len()],
+    /// share of objects only reachable with 1/3 or 1/6 snapping
+    pub triplet_ratio: f32,
+    /// entropy of how each gap relates to the one before it, 0 for a constant rhythm
+    pub change_entropy: f32,
+}
+
+pub fn analyze(hit_objects: &[HitObject], timing: &Timing) -> RhythmStats {
+    if hit_objects.is_empty() {
+        return RhythmStats::default();
+    }
+
+    let mut snap_counts = [0; SNAP_DIVISORS.len()];
+    for obj in hit_objects {
+        if let Some(index) = snap_divisor_index(timing, obj.start_time) {
+            snap_counts[index] += 1;
+        }
+    }
+    let object_count = hit_objects.len() as f32;
+    let snap_histogram = snap_counts.map(|count| count as f32 / object_count);
+    let triplet_ratio = SNAP_DIVISORS
+        .iter()
+        .zip(snap_histogram)
+        .filter(|(&divisor, _)| divisor % 3 == 0)
+        .map(|(_, share)| share)
+        .sum();
+
+    RhythmStats {
+        snap_histogram,
+        triplet_ratio,
+        change_entropy: change_entropy(hit_objects, timing),
+    }
+}
+
+/// Index into `SNAP_DIVISORS` of the coarsest divisor `time` lands on.
+fn snap_divisor_index(timing: &Timing, time: f32) -> Option<usize> {
+    let red_line = timing.uninherited_at(time)?;
+    let beats = (time - red_line.time) / red_line.beat_length;
+    SNAP_DIVISORS.iter().position(|&divisor| {
+        let snaps = beats * divisor as f32;
+        let off_ms = (snaps - snaps.round()).abs() * red_line.beat_length / divisor as f32;
+        off_ms <= SNAP_TOLERANCE_MS
+    })
+}
+
+fn change_entropy(hit_objects: &[HitObject], timing: &Timing) -> f32 {
+    // (previous gap, gap) in ticks, reduced so that 1/4 -> 1/2 and 1/8 -> 1/4 are the same change
+    let mut changes: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut previous_gap: Option<u32> = None;
+    for pair in hit_objects.windows(2) {
+        let beat_length = timing.beat_length_at(pair[1].start_time);
+        let gap_beats = (pair[1].start_time - pair[0].start_time) / beat_length;
+        let gap = (gap_beats * TICKS_PER_BEAT).round() as u32;
+        if gap == 0 || gap_beats > MAX_RHYTHM_GAP_BEATS {
+            previous_gap = None;
+            continue;
+        }
+        if let Some(previous) = previous_gap {
+            let divisor = gcd(previous, gap);
+            *changes
+                .entry((previous / divisor, gap / divisor))
+                .or_default() += 1;
+        }
+        previous_gap = Some(gap);
+    }
+
+    let total = changes.values().sum::<usize>() as f32;
+    let entropy: f32 = changes
+        .values()
+        .map(|&count| {
+            let p = count as f32 / total;
+            p * (1.0 / p).log2()
+        })
+        .sum();
+    (entropy / MAX_ENTROPY_BITS).min(1.0)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
@@ -0,0 +1,275 @@
+use super::{tail_position, turn_between};
+use crate::core::model::{HitObject, HitObjectType};
+use serde::Serialize;
+use std::fmt;
+
+type Point = (f32, f32);
+
+// objects further apart than this (end to start) aren't read as one pattern
+const MAX_PATTERN_GAP_MS: f32 = 600.0;
+// in circle radii
+const MIN_JUMP_RADII: f32 = 2.0;
+const MAX_SIDE_VARIATION: f32 = 0.25;
+const BACK_AND_FORTH_MIN_TURN_DEG: f32 = 165.0;
+const ZIG_ZAG_TURN_DEG: (f32, f32) = (45.0, 165.0);
+const LINEAR_MAX_TURN_DEG: f32 = 15.0;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeKind {
+    Triangle,
+    Square,
+    /// pentagons and pentagrams (stars)
+    Pentagon,
+    Hexagon,
+    BackAndForth,
+    ZigZag,
+    /// a straight line of overlapping objects
+    LinearStack,
+}
+
+impl ShapeKind {
+    /// Feature order of `frequencies`.
+    pub const ALL: [ShapeKind; 7] = [
+        ShapeKind::Triangle,
+        ShapeKind::Square,
+        ShapeKind::Pentagon,
+        ShapeKind::Hexagon,
+        ShapeKind::BackAndForth,
+        ShapeKind::ZigZag,
+        ShapeKind::LinearStack,
+    ];
+
+    fn polygon(corners: usize) -> Option<Self> {
+        match corners {
+            3 => Some(ShapeKind::Triangle),
+            4 => Some(ShapeKind::Square),
+            5 => Some(ShapeKind::Pentagon),
+            6 => Some(ShapeKind::Hexagon),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ShapeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShapeKind::Triangle => "triangle",
+            ShapeKind::Square => "square",
+            ShapeKind::Pentagon => "pentagon/star",
+            ShapeKind::Hexagon => "hexagon",
+            ShapeKind::BackAndForth => "back-and-forth",
+            ShapeKind::ZigZag => "zig-zag",
+            ShapeKind::LinearStack => "linear stack",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One recognised pattern, times in ms.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ShapeEvent {
+    pub kind: ShapeKind,
+    pub start_time: f32,
+    pub end_time: f32,
+    pub object_count: usize,
+    /// index in `hit_objects` of the first and last object (inclusive), chained
+    /// polygons share their closing object
+    pub first_object: usize,
+    pub last_object: usize,
+}
+
+/// Every pattern found in the map, sorted by time. Spinners are ignored.
+pub fn detect(hit_objects: &[HitObject], circle_radius: f32) -> Vec<ShapeEvent> {
+    let (indices, objects): (Vec<usize>, Vec<&HitObject>) = hit_objects
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| o.obj_type != HitObjectType::Spinner)
+        .unzip();
+    if objects.len() < 3 {
+        return Vec::new();
+    }
+    let heads: Vec<Point> = objects.iter().map(|o| (o.x, o.y)).collect();
+    // the cursor leaves sliders from their end
+    let tails: Vec<Point> = objects.iter().map(|o| tail_position(o)).collect();
+    // moves[i]: from the end of object i to the head of object i + 1
+    let moves: Vec<Point> = (0..objects.len() - 1)
+        .map(|i| (heads[i + 1].0 - tails[i].0, heads[i + 1].1 - tails[i].1))
+        .collect();
+    // linked[i]: objects i and i + 1 are close enough in time to be one pattern
+    let linked: Vec<bool> = objects
+        .windows(2)
+        .map(|pair| pair[1].start_time - pair[0].end_time <= MAX_PATTERN_GAP_MS)
+        .collect();
+    let min_jump = MIN_JUMP_RADII * circle_radius;
+
+    let mut events = Vec::new();
+    let mut push_event = |kind, first: usize, last: usize| {
+        events.push(ShapeEvent {
+            kind,
+            start_time: objects[first].start_time,
+            end_time: objects[last].end_time,
+            object_count: last - first + 1,
+            first_object: indices[first],
+            last_object: indices[last],
+        });
+    };
+
+    // closed polygons, the biggest one wins and repeated ones follow each other
+    let mut start = 0;
+    while start < objects.len() {
+        let corners = (3..=6).rev().find(|&corners| {
+            let window = start..=start + corners;
+            window.end() < &objects.len()
+                && linked[start..*window.end()].iter().all(|&l| l)
+                && is_polygon(
+                    &heads[window.clone()],
+                    &moves[start..*window.end()],
+                    circle_radius,
+                    min_jump,
+                )
+        });
+        match corners {
+            Some(corners) => {
+                push_event(ShapeKind::polygon(corners).unwrap(), start, start + corners);
+                start += corners;
+            }
+            None => start += 1,
+        }
+    }
+
+    // the rest are runs of corners that all look alike, corner k sits on object k
+    let turns: Vec<Option<f32>> = (0..objects.len())
+        .map(|k| {
+            if k == 0 || k + 1 == objects.len() || !linked[k - 1] || !linked[k] {
+                return None;
+            }
+            Some(turn_between(moves[k - 1], moves[k]))
+        })
+        .collect();
+    let moves_at = |k: usize| (length(moves[k - 1]), length(moves[k]));
+
+    let back_and_forth = |k: usize, _previous: Option<usize>| {
+        turns[k].is_some_and(|turn| {
+            let (move_in, move_out) = moves_at(k);
+            turn.abs() >= BACK_AND_FORTH_MIN_TURN_DEG
+                && move_in >= min_jump
+                && move_out >= min_jump
+                // lands back where it came from
+                && distance(tails[k - 1], heads[k + 1]) <= circle_radius
+        })
+    };
+    let zig_zag = |k: usize, previous: Option<usize>| {
+        turns[k].is_some_and(|turn| {
+            let (move_in, move_out) = moves_at(k);
+            let alternates = previous
+                .and_then(|previous| turns[previous])
+                .map_or(true, |previous_turn| {
+                    previous_turn.signum() != turn.signum()
+                });
+            (ZIG_ZAG_TURN_DEG.0..=ZIG_ZAG_TURN_DEG.1).contains(&turn.abs())
+                && move_in >= circle_radius
+                && move_out >= circle_radius
+                && alternates
+        })
+    };
+    let linear_stack = |k: usize, _previous: Option<usize>| {
+        turns[k].is_some_and(|turn| {
+            let (move_in, move_out) = moves_at(k);
+            let stacked = move_in < 1.0 || move_out < 1.0;
+            move_in < min_jump
+                && move_out < min_jump
+                && (stacked || turn.abs() <= LINEAR_MAX_TURN_DEG)
+        })
+    };
+
+    for (kind, min_corners, fits) in [
+        (
+            ShapeKind::BackAndForth,
+            2,
+            &back_and_forth as &dyn Fn(usize, Option<usize>) -> bool,
+        ),
+        (ShapeKind::ZigZag, 3, &zig_zag),
+        (ShapeKind::LinearStack, 2, &linear_stack),
+    ] {
+        let mut k = 1;
+        while k + 1 < objects.len() {
+            if !fits(k, None) {
+                k += 1;
+                continue;
+            }
+            let first = k;
+            while k + 2 < objects.len() && fits(k + 1, Some(k)) {
+                k += 1;
+            }
+            if k - first + 1 >= min_corners {
+                // the corners plus the objects before and after them
+                push_event(kind, first - 1, k + 1);
+            }
+            k += 1;
+        }
+    }
+
+    events.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    events
+}
+
+/// Share of objects that are part of each `ShapeKind::ALL` pattern, all in 0..1.
+/// Objects shared by two events of the same kind only count once.
+pub fn frequencies(events: &[ShapeEvent], object_count: usize) -> [f32; ShapeKind::ALL.len()] {
+    let mut in_kind = vec![[false; ShapeKind::ALL.len()]; object_count];
+    for event in events {
+        let index = ShapeKind::ALL
+            .iter()
+            .position(|&kind| kind == event.kind)
+            .unwrap();
+        for object in &mut in_kind[event.first_object..=event.last_object] {
+            object[index] = true;
+        }
+    }
+    let mut objects_per_kind = [0; ShapeKind::ALL.len()];
+    for object in &in_kind {
+        for (count, &is_in) in objects_per_kind.iter_mut().zip(object) {
+            *count += is_in as usize;
+        }
+    }
+    objects_per_kind.map(|objects| objects as f32 / object_count.max(1) as f32)
+}
+
+/// `heads` are the objects around the polygon, the last one being back at the
+/// first, `moves` the movements between them.
+fn is_polygon(heads: &[Point], moves: &[Point], circle_radius: f32, min_jump: f32) -> bool {
+    let corners = &heads[..heads.len() - 1];
+    if distance(heads[0], heads[corners.len()]) > circle_radius {
+        return false;
+    }
+    // every corner is its own spot, not a stack or a back-and-forth
+    for (i, &a) in corners.iter().enumerate() {
+        if corners[i + 1..].iter().any(|&b| distance(a, b) < min_jump) {
+            return false;
+        }
+    }
+
+    let sides: Vec<f32> = moves.iter().map(|&side| length(side)).collect();
+    let mean = sides.iter().sum::<f32>() / sides.len() as f32;
+    let variance = sides.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / sides.len() as f32;
+    if variance.sqrt() / mean > MAX_SIDE_VARIATION {
+        return false;
+    }
+
+    // always turning the same way (also true for stars), the last move leads back
+    // into the first corner
+    let count = moves.len();
+    let turns: Vec<f32> = (0..count)
+        .map(|i| turn_between(moves[(i + count - 1) % count], moves[i]))
+        .collect();
+    turns.iter().all(|&turn| turn > 0.0) || turns.iter().all(|&turn| turn < 0.0)
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    length((a.0 - b.0, a.1 - b.1))
+}
+
+fn length(vector: Point) -> f32 {
+    vector.0.hypot(vector.1)
+}
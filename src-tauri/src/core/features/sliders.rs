@@ -0,0 +1,119 @@
+use crate::core::model::{CurveType, HitObject};
+use std::f32::consts::PI;
+
+// normalization caps, values above these all look the same
+const CURVATURE_REFERENCE_PX: f32 = 100.0; // turning measured per 100 px of body
+const MAX_ANCHORS: f32 = 10.0;
+const MAX_EXTRA_LENGTH_RATIO: f32 = 2.0;
+// 3+ bezier segments or lots of anchors is blanket/art territory
+const ART_MIN_SEGMENTS: usize = 3;
+const ART_MIN_ANCHORS: usize = 8;
+
+/// Slider shape features, all in 0..1.
+#[derive(Debug, Default)]
+pub struct SliderShapeStats {
+    pub mean_curvature: f32,
+    pub max_curvature: f32,
+    pub anchors_per_slider: f32,
+    /// how much longer the body is than the straight line from head to tail
+    pub curve_length_ratio: f32,
+    pub art_slider_ratio: f32,
+    /// coefficient of variation of slider velocities
+    pub velocity_variation: f32,
+}
+
+pub fn analyze(sliders: &[&HitObject]) -> SliderShapeStats {
+    let mut curvatures = Vec::with_capacity(sliders.len());
+    let mut anchor_total = 0;
+    let mut extra_length_total = 0.0;
+    let mut art_sliders = 0;
+    let mut velocities = Vec::with_capacity(sliders.len());
+
+    for obj in sliders {
+        let Some(slider) = &obj.slider else {
+            continue;
+        };
+        let anchors = obj.curve_points.as_ref().map_or(0, |points| points.len());
+        anchor_total += anchors;
+        velocities.push(slider.velocity);
+
+        let points = slider.path.points();
+        let length = slider.path.length();
+        if length <= 0.0 || points.len() < 2 {
+            continue;
+        }
+        curvatures.push(total_turning(points) / length * CURVATURE_REFERENCE_PX / PI);
+
+        let (tail_x, tail_y) = slider.path.position_at(1.0);
+        let straight = ((tail_x - obj.x).powi(2) + (tail_y - obj.y).powi(2)).sqrt();
+        // sliders that end on their head (circles, loops) get the max ratio
+        extra_length_total += if straight > 1.0 {
+            ((length / straight - 1.0) / MAX_EXTRA_LENGTH_RATIO).min(1.0)
+        } else {
+            1.0
+        };
+
+        let segments = match slider.curve_type {
+            CurveType::Bezier => red_anchor_count(obj) + 1,
+            _ => 1,
+        };
+        if segments >= ART_MIN_SEGMENTS || anchors >= ART_MIN_ANCHORS {
+            art_sliders += 1;
+        }
+    }
+
+    if velocities.is_empty() {
+        return SliderShapeStats::default();
+    }
+    let slider_count = velocities.len() as f32;
+    let shaped_count = curvatures.len().max(1) as f32;
+
+    SliderShapeStats {
+        mean_curvature: (curvatures.iter().sum::<f32>() / shaped_count).min(1.0),
+        max_curvature: curvatures.iter().copied().fold(0.0, f32::max).min(1.0),
+        anchors_per_slider: (anchor_total as f32 / slider_count / MAX_ANCHORS).min(1.0),
+        curve_length_ratio: extra_length_total / shaped_count,
+        art_slider_ratio: art_sliders as f32 / slider_count,
+        velocity_variation: coefficient_of_variation(&velocities).min(1.0),
+    }
+}
+
+fn red_anchor_count(obj: &HitObject) -> usize {
+    obj.curve_points.as_ref().map_or(0, |points| {
+        let head = (obj.x, obj.y);
+        let first_is_red = points.first() == Some(&head);
+        first_is_red as usize + points.windows(2).filter(|pair| pair[0] == pair[1]).count()
+    })
+}
+
+/// Sum of the absolute direction changes along the polyline, in radians.
+fn total_turning(points: &[(f32, f32)]) -> f32 {
+    let mut turning = 0.0;
+    let mut previous_direction: Option<f32> = None;
+    for pair in points.windows(2) {
+        let (dx, dy) = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+        if dx == 0.0 && dy == 0.0 {
+            continue;
+        }
+        let direction = dy.atan2(dx);
+        if let Some(previous) = previous_direction {
+            let mut delta = (direction - previous).abs();
+            if delta > PI {
+                delta = 2.0 * PI - delta;
+            }
+            turning += delta;
+        }
+        previous_direction = Some(direction);
+    }
+    turning
+}
+
+fn coefficient_of_variation(values: &[f32]) -> f32 {
+    let count = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / count;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / count;
+    variance.sqrt() / mean
+}
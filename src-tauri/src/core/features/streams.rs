@@ -0,0 +1,77 @@
+use crate::core::model::{HitObject, HitObjectType};
+
+// run lengths in objects, anything shorter than a burst is a double/triple tap
+const BURST_MIN_OBJECTS: usize = 3;
+const SHORT_STREAM_MIN_OBJECTS: usize = 9;
+const LONG_STREAM_MIN_OBJECTS: usize = 17;
+const DEATHSTREAM_MIN_OBJECTS: usize = 33;
+const MAX_STREAM_OBJECTS: f32 = 128.0;
+
+/// Share of all objects that are part of a stream of each length, all in 0..1.
+#[derive(Debug, Default)]
+pub struct StreamStats {
+    /// 3-8 objects
+    pub burst_ratio: f32,
+    /// 9-16 objects
+    pub short_stream_ratio: f32,
+    /// 17-32 objects
+    pub long_stream_ratio: f32,
+    /// 33+ objects
+    pub deathstream_ratio: f32,
+    /// normalized by `MAX_STREAM_OBJECTS`
+    pub longest_stream: f32,
+}
+
+/// Runs of objects following each other within `max_gap_ms` (from the end of the
+/// previous object). Spinners break a run.
+pub fn analyze(hit_objects: &[HitObject], max_gap_ms: f32) -> StreamStats {
+    if hit_objects.is_empty() {
+        return StreamStats::default();
+    }
+
+    let mut run_lengths = Vec::new();
+    let mut run_length = 1;
+    let is_spinner = |obj: &HitObject| obj.obj_type == HitObjectType::Spinner;
+    for pair in hit_objects.windows(2) {
+        let gap = pair[1].start_time - pair[0].end_time;
+        if gap > 0.0 && gap < max_gap_ms && !is_spinner(&pair[0]) && !is_spinner(&pair[1]) {
+            run_length += 1;
+        } else {
+            run_lengths.push(run_length);
+            run_length = 1;
+        }
+    }
+    run_lengths.push(run_length);
+
+    let mut objects_per_bucket = [0; 4];
+    for &length in &run_lengths {
+        let bucket = if length >= DEATHSTREAM_MIN_OBJECTS {
+            3
+        } else if length >= LONG_STREAM_MIN_OBJECTS {
+            2
+        } else if length >= SHORT_STREAM_MIN_OBJECTS {
+            1
+        } else if length >= BURST_MIN_OBJECTS {
+            0
+        } else {
+            continue;
+        };
+        objects_per_bucket[bucket] += length;
+    }
+    let object_count = hit_objects.len() as f32;
+    let [burst, short_stream, long_stream, deathstream] =
+        objects_per_bucket.map(|objects| objects as f32 / object_count);
+
+    let longest = run_lengths.iter().copied().max().unwrap_or(0);
+    StreamStats {
+        burst_ratio: burst,
+        short_stream_ratio: short_stream,
+        long_stream_ratio: long_stream,
+        deathstream_ratio: deathstream,
+        longest_stream: if longest >= BURST_MIN_OBJECTS {
+            (longest as f32 / MAX_STREAM_OBJECTS).min(1.0)
+        } else {
+            0.0
+        },
+    }
+}
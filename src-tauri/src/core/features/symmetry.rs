@@ -0,0 +1,86 @@
+use crate::core::model::{HitObject, HitObjectType};
+
+const PLAYFIELD_WIDTH: f32 = 512.0;
+const PLAYFIELD_HEIGHT: f32 = 384.0;
+
+const WINDOW_SIZE: usize = 8;
+const WINDOW_STRIDE: usize = 4;
+// share of a window's points that need a mirrored partner to count as symmetric
+const MIN_MATCHED_RATIO: f32 = 0.75;
+
+/// Share of object windows that are mirrored across the playfield axes, all in 0..1.
+#[derive(Debug, Default)]
+pub struct SymmetryStats {
+    /// left/right halves mirror each other (x -> 512 - x)
+    pub horizontal: f32,
+    /// top/bottom halves mirror each other (y -> 384 - y)
+    pub vertical: f32,
+    /// 180° rotation around the playfield center
+    pub point: f32,
+}
+
+pub fn analyze(hit_objects: &[HitObject], tolerance: f32) -> SymmetryStats {
+    let objects: Vec<&HitObject> = hit_objects
+        .iter()
+        .filter(|o| o.obj_type != HitObjectType::Spinner)
+        .collect();
+    if objects.len() < WINDOW_SIZE {
+        return SymmetryStats::default();
+    }
+
+    let mut window_count = 0;
+    let mut horizontal = 0;
+    let mut vertical = 0;
+    let mut point = 0;
+    for start in (0..=objects.len() - WINDOW_SIZE).step_by(WINDOW_STRIDE) {
+        window_count += 1;
+        // slider ends are part of the shape too
+        let points: Vec<(f32, f32)> = objects[start..start + WINDOW_SIZE]
+            .iter()
+            .flat_map(|o| {
+                let tail = o.slider.as_ref().map(|slider| slider.end_position);
+                std::iter::once((o.x, o.y)).chain(tail)
+            })
+            .collect();
+
+        if is_symmetric(&points, tolerance, |(x, y)| (PLAYFIELD_WIDTH - x, y)) {
+            horizontal += 1;
+        }
+        if is_symmetric(&points, tolerance, |(x, y)| (x, PLAYFIELD_HEIGHT - y)) {
+            vertical += 1;
+        }
+        if is_symmetric(&points, tolerance, |(x, y)| {
+            (PLAYFIELD_WIDTH - x, PLAYFIELD_HEIGHT - y)
+        }) {
+            point += 1;
+        }
+    }
+
+    let window_count = window_count as f32;
+    SymmetryStats {
+        horizontal: horizontal as f32 / window_count,
+        vertical: vertical as f32 / window_count,
+        point: point as f32 / window_count,
+    }
+}
+
+/// Points on the mirror axis are their own mirror image, so they only count
+/// against the window instead of proving anything.
+fn is_symmetric(
+    points: &[(f32, f32)],
+    tolerance: f32,
+    mirror: impl Fn((f32, f32)) -> (f32, f32),
+) -> bool {
+    let tolerance_sq = tolerance * tolerance;
+    let is_near = |(ax, ay): (f32, f32), (bx, by): (f32, f32)| {
+        (ax - bx).powi(2) + (ay - by).powi(2) <= tolerance_sq
+    };
+    let matched = points
+        .iter()
+        .filter(|&&p| {
+            let mirrored = mirror(p);
+            !is_near(p, mirrored) && points.iter().any(|&other| is_near(other, mirrored))
+        })
+        .count();
+    matched as f32 >= points.len() as f32 * MIN_MATCHED_RATIO
+}
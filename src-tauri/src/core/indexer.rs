@@ -1,98 +1,516 @@
-use super::model::IndexingProgress;
-use super::{features, model::MapInfo, parser};
-use anyhow::Result;
+use super::model::{
+    FailureReason, GameMode, IndexedMap, IndexingIssue, IndexingOutcome, IndexingReport,
+    ManifestEntry,
+};
+use super::progress::{IndexingPhase, ProgressReporter, ProgressSink};
+use super::{difficulty, features, model::MapInfo, parser, storage};
+use anyhow::{bail, Context, Result};
 use rayon::prelude::*;
 use seli_vector_db::{Vector, VectorDB};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+    Arc,
 };
+use std::time::{SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+/// Indexes every `.osu` file under `songs_path`. With `incremental` set, files
+/// whose mtime/size (or content hash) match the previous run's manifest are
+/// reused instead of being parsed again.
+///
+/// Setting `cancel_flag` stops the run early without touching the database
+/// that is already on disk. The returned report is also saved next to the
+/// database, whatever the outcome.
 pub fn run_indexing(
     songs_path: &Path,
     db_path: &Path,
     limit: Option<usize>,
-    progress_tracker: Option<Arc<Mutex<IndexingProgress>>>,
-) -> Result<()> {
+    incremental: bool,
+    progress_sink: Option<&dyn ProgressSink>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+) -> IndexingReport {
+    let started = SystemTime::now();
+    let mut report = IndexingReport {
+        started_at: started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+        ..Default::default()
+    };
+
+    match index_songs(
+        songs_path,
+        db_path,
+        limit,
+        incremental,
+        progress_sink,
+        cancel_flag,
+        &mut report,
+    ) {
+        Ok(outcome) => report.outcome = outcome,
+        Err(e) => {
+            eprintln!("Indexing failed: {:#}", e);
+            report.outcome = IndexingOutcome::Failed;
+            report.error = Some(format!("{:#}", e));
+        }
+    }
+    report.duration_ms = started.elapsed().map_or(0, |d| d.as_millis() as u64);
+
+    if let Err(e) = save_report(db_path, &report) {
+        eprintln!("Failed to save indexing report: {}", e);
+    }
+    report
+}
+
+fn index_songs(
+    songs_path: &Path,
+    db_path: &Path,
+    limit: Option<usize>,
+    incremental: bool,
+    progress_sink: Option<&dyn ProgressSink>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    report: &mut IndexingReport,
+) -> Result<IndexingOutcome> {
+    // files past the limit would look deleted and drop out of the manifest
+    if incremental && limit.is_some() {
+        bail!("An incremental run can't be limited, index everything or turn incremental off.");
+    }
+
+    let progress = ProgressReporter::new(progress_sink);
+    let is_cancelled = || {
+        cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    };
+
     println!(
         "Phase 1: Indexing beatmaps from '{}'...",
         songs_path.display()
     );
 
+    progress.start_phase(IndexingPhase::Walk, 0);
     let osu_files_iter = WalkDir::new(songs_path)
         .into_iter()
+        .take_while(|_| !is_cancelled())
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "osu"));
+        .filter(|e| is_osu_file(e.path()))
+        .inspect(|_| progress.advance(1));
 
     let osu_files: Vec<_> = if let Some(l) = limit {
         osu_files_iter.take(l).collect()
     } else {
         osu_files_iter.collect()
     };
-
-    if let Some(tracker) = &progress_tracker {
-        let mut progress = tracker.lock().unwrap();
-        progress.total = osu_files.len() as u64;
-        progress.message = "Parsing beatmaps...".to_string();
+    progress.finish_phase();
+    if is_cancelled() {
+        println!("Indexing cancelled.");
+        return Ok(IndexingOutcome::Cancelled);
     }
+    report.total_files = osu_files.len();
+
+    let previous: HashMap<PathBuf, ManifestEntry> = if incremental {
+        load_manifest(db_path)
+            .map(|entries| entries.into_iter().map(|e| (e.path.clone(), e)).collect())
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
 
-    let maps_processed = AtomicUsize::new(0);
-    let indexed_data: Vec<(Vector, MapInfo)> = osu_files
+    progress.start_phase(IndexingPhase::Parse, osu_files.len() as u64);
+    let scanned: Vec<(&Path, Result<ScannedFile, FailureReason>)> = osu_files
         .par_iter()
-        .filter_map(|entry| {
-            if let Some(tracker) = &progress_tracker {
-                let count = maps_processed.fetch_add(1, Ordering::Relaxed);
-                if count % 100 == 0 {
-                    tracker.lock().unwrap().progress = count as u64;
-                }
+        .map(|entry| {
+            // rayon can't break out of a par_iter, so just skip everything that's left
+            if is_cancelled() {
+                return None;
             }
-            let beatmap = parser::parse_beatmap_from_file(entry.path()).ok()?;
-            let feature_vector = features::extract_features(&beatmap)?;
-            let map_info = MapInfo {
-                path: entry.path().to_path_buf(),
-                beatmap_id: beatmap.beatmap_id,
-                beatmapset_id: beatmap.beatmapset_id,
-                title: beatmap.title,
-                artist: beatmap.artist,
-                difficulty_name: beatmap.difficulty_name,
-            };
-            Some((feature_vector, map_info))
+            let path = entry.path();
+            let scanned = scan_file(path, previous.get(path));
+            progress.advance(1);
+            Some((path, scanned))
         })
+        .while_some()
         .collect();
+    progress.finish_phase();
+    if is_cancelled() {
+        println!("Indexing cancelled.");
+        return Ok(IndexingOutcome::Cancelled);
+    }
 
-    let (vectors, map_infos): (Vec<Vector>, Vec<MapInfo>) = indexed_data.into_iter().unzip();
+    let mut entries = Vec::with_capacity(scanned.len());
+    let mut parsed_files = 0;
+    for (path, result) in scanned {
+        let entry = match result {
+            Ok(scanned_file) => {
+                if scanned_file.parsed {
+                    parsed_files += 1;
+                }
+                if scanned_file.parsed && scanned_file.entry.indexed.is_some() {
+                    report.parsed += 1;
+                } else if scanned_file.entry.indexed.is_some() {
+                    report.reused += 1;
+                }
+                scanned_file.entry
+            }
+            Err(reason) => {
+                report.failed += 1;
+                report.issues.push(IndexingIssue {
+                    path: path.to_path_buf(),
+                    reason,
+                });
+                continue;
+            }
+        };
+        if let Some(reason) = &entry.failure {
+            if reason.is_skip() {
+                report.skipped += 1;
+            } else {
+                report.failed += 1;
+            }
+            report.issues.push(IndexingIssue {
+                path: entry.path.clone(),
+                reason: reason.clone(),
+            });
+        }
+        entries.push(entry);
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    report.issues.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let current_paths: HashSet<&Path> = osu_files.iter().map(|e| e.path()).collect();
+    report.removed = previous
+        .keys()
+        .filter(|path| !current_paths.contains(path.as_path()))
+        .count();
+    println!(
+        "{} parsed, {} unchanged, {} skipped, {} failed, {} removed.",
+        report.parsed, report.reused, report.skipped, report.failed, report.removed
+    );
+
+    let (vectors, map_infos) = split_entries(&entries);
+    report.indexed = map_infos.len();
+    let index_is_current =
+        storage::read_header(db_path).is_ok_and(|header| header.check_compatible().is_ok());
+    let has_changes = parsed_files > 0 || entries.len() != previous.len() || !index_is_current;
+    if incremental && !has_changes {
+        println!("Index is already up to date.");
+        return Ok(IndexingOutcome::Completed);
+    }
     println!("Total maps to be indexed: {}", map_infos.len());
 
     if !map_infos.is_empty() {
-        if let Some(tracker) = &progress_tracker {
-            let mut progress = tracker.lock().unwrap();
-            progress.progress = progress.total;
-            progress.message = "Building search index...".to_string();
+        progress.start_phase(IndexingPhase::Cluster, 1);
+        let db = build_db(&vectors)?;
+        progress.finish_phase();
+        if is_cancelled() {
+            println!("Indexing cancelled.");
+            return Ok(IndexingOutcome::Cancelled);
         }
 
-        println!("Phase 2: Building search index...");
-        let mut db = VectorDB::new();
-        for v in vectors {
-            db.add(v);
+        progress.start_phase(IndexingPhase::Save, 1);
+        save_index(db_path, &db, &map_infos, &vectors, &entries)?;
+        progress.finish_phase();
+        println!("Database and paths saved successfully!");
+    } else {
+        println!("No maps were found to index.");
+        clear_index(db_path, &entries)?;
+    }
+
+    Ok(IndexingOutcome::Completed)
+}
+
+pub struct IndexUpdate {
+    /// `None` when no indexed maps are left, the index file is gone then
+    pub db: Option<VectorDB>,
+    pub map_infos: Vec<MapInfo>,
+    pub vectors: Vec<Vector>,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+/// Re-scans only `changed_paths` against `entries` (a loaded manifest, sorted by
+/// path) and builds the new search index if anything actually changed. Paths can
+/// be `.osu` files or whole beatmapset folders, missing paths are treated as
+/// deleted. `entries` is updated in place, nothing is written to disk until
+/// `save_update`.
+pub fn prepare_changes(
+    entries: &mut Vec<ManifestEntry>,
+    changed_paths: &[PathBuf],
+) -> Result<Option<IndexUpdate>> {
+    let mut osu_files = Vec::new();
+    let mut removed = 0;
+    for path in changed_paths {
+        if path.is_dir() {
+            osu_files.extend(
+                WalkDir::new(path)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| is_osu_file(e.path()))
+                    .map(|e| e.into_path()),
+            );
+        } else if path.is_file() {
+            if is_osu_file(path) {
+                osu_files.push(path.clone());
+            }
+        } else {
+            let before = entries.len();
+            entries.retain(|entry| !entry.path.starts_with(path));
+            removed += before - entries.len();
         }
+    }
+    osu_files.sort();
+    osu_files.dedup();
 
-        let num_clusters = (db.len() as f64).sqrt() as usize;
-        db.build_index(num_clusters, 20)?;
-        println!("Index built with {} clusters.", num_clusters);
+    let find = |entries: &[ManifestEntry], path: &Path| {
+        entries.binary_search_by(|entry| entry.path.as_path().cmp(path))
+    };
+    let scanned: Vec<(&PathBuf, Result<ScannedFile, FailureReason>)> = osu_files
+        .par_iter()
+        .map(|path| {
+            let previous = find(entries, path).ok().map(|i| &entries[i]);
+            (path, scan_file(path, previous))
+        })
+        .collect();
+    let mut updated = 0;
+    for (path, scanned_file) in scanned {
+        let scanned_file = match scanned_file {
+            Ok(scanned_file) => scanned_file,
+            Err(reason) => {
+                eprintln!("Failed to read '{}': {}", path.display(), reason);
+                continue;
+            }
+        };
+        if scanned_file.parsed {
+            updated += 1;
+        }
+        match find(entries, path) {
+            Ok(i) => entries[i] = scanned_file.entry,
+            Err(i) => entries.insert(i, scanned_file.entry),
+        }
+    }
 
-        println!("Saving database to '{}'...", db_path.display());
-        db.save_to_file(db_path)?;
-        let paths_path = db_path.with_extension("paths.json");
-        let infos_json = serde_json::to_string_pretty(&map_infos)?;
-        fs::write(paths_path, infos_json)?;
+    if updated == 0 && removed == 0 {
+        return Ok(None);
+    }
 
-        println!("Database and paths saved successfully!");
+    let (vectors, map_infos) = split_entries(entries);
+    let db = if map_infos.is_empty() {
+        None
     } else {
-        println!("No maps were found to index.");
+        Some(build_db(&vectors)?)
+    };
+    Ok(Some(IndexUpdate {
+        db,
+        map_infos,
+        vectors,
+        updated,
+        removed,
+    }))
+}
+
+/// Writes what `prepare_changes` built, `entries` being the updated manifest.
+pub fn save_update(db_path: &Path, update: &IndexUpdate, entries: &[ManifestEntry]) -> Result<()> {
+    match &update.db {
+        Some(db) => save_index(db_path, db, &update.map_infos, &update.vectors, entries),
+        None => clear_index(db_path, entries),
+    }
+}
+
+fn split_entries(entries: &[ManifestEntry]) -> (Vec<Vector>, Vec<MapInfo>) {
+    entries
+        .iter()
+        .filter_map(|e| e.indexed.as_ref())
+        .map(|indexed| (indexed.vector.clone(), indexed.map_info.clone()))
+        .unzip()
+}
+
+fn build_db(vectors: &[Vector]) -> Result<VectorDB> {
+    println!("Phase 2: Building search index...");
+    let mut db = VectorDB::new();
+    for v in vectors {
+        db.add(v.clone())?;
+    }
+
+    let num_clusters = (db.len() as f64).sqrt() as usize;
+    db.build_index(num_clusters, 20)?;
+    println!("Index built with {} clusters.", num_clusters);
+    Ok(db)
+}
+
+/// Removes the index file when there's nothing left to search, so deleted maps
+/// don't keep showing up. The manifest still remembers the skipped/failed files.
+fn clear_index(db_path: &Path, entries: &[ManifestEntry]) -> Result<()> {
+    match fs::remove_file(db_path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to remove '{}'", db_path.display()))
+        }
     }
+    save_manifest(db_path, entries)
+}
+
+fn save_index(
+    db_path: &Path,
+    db: &VectorDB,
+    map_infos: &[MapInfo],
+    vectors: &[Vector],
+    entries: &[ManifestEntry],
+) -> Result<()> {
+    println!("Saving database to '{}'...", db_path.display());
+    storage::save(db_path, db, map_infos, vectors)?;
+    save_manifest(db_path, entries)?;
+
+    Ok(())
+}
+
+fn is_osu_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "osu")
+}
 
+pub fn load_report(db_path: &Path) -> Result<IndexingReport> {
+    let report_path = db_path.with_extension("report.json");
+    let contents = fs::read_to_string(&report_path).context(format!(
+        "Failed to load indexing report from '{}'",
+        report_path.display()
+    ))?;
+    serde_json::from_str(&contents).context("Failed to parse indexing report JSON.")
+}
+
+fn save_report(db_path: &Path, report: &IndexingReport) -> Result<()> {
+    let report_path = db_path.with_extension("report.json");
+    fs::write(report_path, serde_json::to_string_pretty(report)?)?;
     Ok(())
 }
+
+#[derive(Serialize, Deserialize)]
+struct Manifest<'a> {
+    feature_fingerprint: String,
+    /// manifests from before this was stored count as outdated
+    #[serde(default)]
+    difficulty_version: u32,
+    entries: Cow<'a, [ManifestEntry]>,
+}
+
+/// Fails if the cached vectors were built by another feature schema (or the
+/// attributes by another difficulty estimator), in which case every file has
+/// to be parsed again anyway.
+pub fn load_manifest(db_path: &Path) -> Result<Vec<ManifestEntry>> {
+    let manifest_path = db_path.with_extension("manifest.json");
+    let contents = fs::read_to_string(&manifest_path).context(format!(
+        "Failed to load manifest from '{}'",
+        manifest_path.display()
+    ))?;
+    let manifest: Manifest =
+        serde_json::from_str(&contents).context("Failed to parse manifest JSON.")?;
+    if manifest.feature_fingerprint != features::schema_fingerprint() {
+        bail!("Manifest was built by another version of the feature extractor.");
+    }
+    if manifest.difficulty_version != difficulty::DIFFICULTY_VERSION {
+        bail!("Manifest was built by another version of the difficulty estimator.");
+    }
+    Ok(manifest.entries.into_owned())
+}
+
+fn save_manifest(db_path: &Path, entries: &[ManifestEntry]) -> Result<()> {
+    let manifest_path = db_path.with_extension("manifest.json");
+    let manifest = Manifest {
+        feature_fingerprint: features::schema_fingerprint(),
+        difficulty_version: difficulty::DIFFICULTY_VERSION,
+        entries: Cow::Borrowed(entries),
+    };
+    fs::write(manifest_path, serde_json::to_string(&manifest)?)?;
+    Ok(())
+}
+
+struct ScannedFile {
+    entry: ManifestEntry,
+    /// `false` when the previous manifest entry could be reused as is
+    parsed: bool,
+}
+
+/// Builds the manifest entry for `path`. Parse failures still produce an entry
+/// (with `failure` set), only unreadable files return an error.
+fn scan_file(path: &Path, previous: Option<&ManifestEntry>) -> Result<ScannedFile, FailureReason> {
+    let metadata = fs::metadata(path).map_err(|e| FailureReason::Io(e.to_string()))?;
+    let size = metadata.len();
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_millis() as u64);
+
+    if let Some(prev) = previous {
+        if prev.modified_ms == modified_ms && prev.size == size {
+            return Ok(ScannedFile {
+                entry: prev.clone(),
+                parsed: false,
+            });
+        }
+    }
+
+    let bytes = fs::read(path).map_err(|e| FailureReason::Io(e.to_string()))?;
+    let md5 = format!("{:x}", md5::compute(&bytes));
+
+    // touched but not edited (e.g. copied back from a backup)
+    if let Some(prev) = previous.filter(|prev| prev.md5 == md5) {
+        let entry = ManifestEntry {
+            modified_ms,
+            size,
+            ..prev.clone()
+        };
+        return Ok(ScannedFile {
+            entry,
+            parsed: false,
+        });
+    }
+
+    let (indexed, failure) = match index_beatmap(path, bytes) {
+        Ok(indexed) => (Some(indexed), None),
+        Err(reason) => (None, Some(reason)),
+    };
+
+    let entry = ManifestEntry {
+        path: path.to_path_buf(),
+        modified_ms,
+        size,
+        md5,
+        indexed,
+        failure,
+    };
+    Ok(ScannedFile {
+        entry,
+        parsed: true,
+    })
+}
+
+fn index_beatmap(path: &Path, bytes: Vec<u8>) -> Result<IndexedMap, FailureReason> {
+    let contents = String::from_utf8(bytes).map_err(|_| FailureReason::Encoding)?;
+    let beatmap = parser::parse_beatmap_from_str(&contents)
+        .map_err(|e| FailureReason::Parse(e.to_string()))?;
+    if beatmap.mode != GameMode::Osu {
+        return Err(FailureReason::UnsupportedMode(format!(
+            "{:?}",
+            beatmap.mode
+        )));
+    }
+    let vector = features::extract_features(&beatmap).ok_or(FailureReason::EmptyHitObjects)?;
+    let attributes = difficulty::calculate(&beatmap);
+
+    let map_info = MapInfo {
+        path: path.to_path_buf(),
+        beatmap_id: beatmap.beatmap_id,
+        beatmapset_id: beatmap.beatmapset_id,
+        title: beatmap.title,
+        artist: beatmap.artist,
+        difficulty_name: beatmap.difficulty_name,
+        difficulty: beatmap.difficulty,
+        timing: beatmap.timing.summary(&beatmap.hit_objects),
+        attributes,
+    };
+    Ok(IndexedMap { map_info, vector })
+}
@@ -1,6 +1,11 @@
 pub mod config;
+pub mod difficulty;
 pub mod features;
 pub mod indexer;
 pub mod model;
 pub mod parser;
+pub mod progress;
 pub mod searcher;
+pub mod slider_path;
+pub mod storage;
+pub mod timing;
@@ -1,16 +1,58 @@
+use super::difficulty::DifficultyAttributes;
+use super::slider_path::{SliderPath, SliderTick};
+use super::timing::{Timing, TimingSummary};
+use seli_vector_db::Vector;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameMode {
+    #[default]
+    Osu,
+    Taiko,
+    Catch,
+    Mania,
+}
+
 #[derive(Debug, Default)]
 pub struct Beatmap {
+    pub mode: GameMode,
     pub title: String,
     pub artist: String,
     pub difficulty_name: String,
     pub beatmap_id: i32,
     pub beatmapset_id: i32,
+    pub difficulty: Difficulty,
+    pub timing: Timing,
     pub hit_objects: Vec<HitObject>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Difficulty {
+    pub circle_size: f32,
+    pub approach_rate: f32,
+    pub overall_difficulty: f32,
+    pub hp_drain_rate: f32,
+    pub slider_multiplier: f32,
+    pub slider_tick_rate: f32,
+}
+
+impl Difficulty {
+    /// Hit circle radius in osu!pixels.
+    pub fn circle_radius(&self) -> f32 {
+        54.4 - 4.48 * self.circle_size
+    }
+
+    /// How long before its hit time an object fades in, in ms.
+    pub fn preempt_ms(&self) -> f32 {
+        if self.approach_rate < 5.0 {
+            1200.0 + 600.0 * (5.0 - self.approach_rate) / 5.0
+        } else {
+            1200.0 - 750.0 * (self.approach_rate - 5.0) / 5.0
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HitObjectType {
     Circle,
@@ -19,13 +61,36 @@ pub enum HitObjectType {
     HoldNote,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveType {
+    Linear,
+    Bezier,
+    PerfectCircle,
+    Catmull,
+}
+
+#[derive(Debug, Clone)]
+pub struct SliderData {
+    pub curve_type: CurveType,
+    /// number of times the slider body is traversed (1 = no repeats)
+    pub span_count: u32,
+    /// osu!pixels per ms
+    pub velocity: f32,
+    pub path: SliderPath,
+    pub end_position: (f32, f32),
+    pub ticks: Vec<SliderTick>,
+}
+
 #[derive(Debug, Clone)]
 pub struct HitObject {
     pub x: f32,
     pub y: f32,
     pub start_time: f32,
+    /// same as `start_time` for circles
+    pub end_time: f32,
     pub obj_type: HitObjectType,
     pub curve_points: Option<Vec<(f32, f32)>>,
+    pub slider: Option<SliderData>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -36,13 +101,90 @@ pub struct MapInfo {
     pub title: String,
     pub artist: String,
     pub difficulty_name: String,
+    pub difficulty: Difficulty,
+    pub timing: TimingSummary,
+    pub attributes: DifficultyAttributes,
 }
 
-#[derive(Serialize, Clone, Default, Debug)]
-pub struct IndexingProgress {
-    pub progress: u64,
-    pub total: u64,
-    pub message: String,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexedMap {
+    pub map_info: MapInfo,
+    pub vector: Vector,
+}
+
+/// One `.osu` file seen by the last indexing run. `indexed` is `None` when the
+/// file couldn't be parsed, so unchanged broken files aren't retried every time.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub modified_ms: u64,
+    pub size: u64,
+    pub md5: String,
+    pub indexed: Option<IndexedMap>,
+    pub failure: Option<FailureReason>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexingOutcome {
+    #[default]
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Why a `.osu` file didn't make it into the index.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "detail")]
+pub enum FailureReason {
+    Io(String),
+    Encoding,
+    Parse(String),
+    EmptyHitObjects,
+    UnsupportedMode(String),
+}
+
+impl FailureReason {
+    /// Skipped files are valid beatmaps we just don't index, everything else is a failure.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            FailureReason::EmptyHitObjects | FailureReason::UnsupportedMode(_)
+        )
+    }
+}
+
+impl std::fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureReason::Io(e) => write!(f, "I/O error: {}", e),
+            FailureReason::Encoding => write!(f, "file is not valid UTF-8"),
+            FailureReason::Parse(e) => write!(f, "parse error: {}", e),
+            FailureReason::EmptyHitObjects => write!(f, "no hit objects"),
+            FailureReason::UnsupportedMode(mode) => write!(f, "unsupported mode: {}", mode),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexingIssue {
+    pub path: PathBuf,
+    pub reason: FailureReason,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IndexingReport {
+    pub outcome: IndexingOutcome,
+    pub error: Option<String>,
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub total_files: usize,
+    pub parsed: usize,
+    pub reused: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub removed: usize,
+    pub indexed: usize,
+    pub issues: Vec<IndexingIssue>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -1,103 +1,243 @@
-use super::model::{Beatmap, HitObject, HitObjectType};
+use super::model::{
+    Beatmap, CurveType, Difficulty, GameMode, HitObject, HitObjectType, SliderData,
+};
+use super::slider_path::SliderPath;
+use super::timing::{InheritedPoint, KiaiSection, Timing, UninheritedPoint};
 use anyhow::anyhow;
 use std::path::Path;
 
+const OSU_FILE_HEADER: &str = "osu file format";
+
+/// Accepts either a path to a `.osu` file or the raw contents of one.
+pub fn parse_beatmap_from_source(source: &str) -> Result<Beatmap, anyhow::Error> {
+    if source
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with(OSU_FILE_HEADER)
+    {
+        parse_beatmap_from_str(source)
+    } else {
+        parse_beatmap_from_file(Path::new(source.trim()))
+    }
+}
+
 pub fn parse_beatmap_from_file(path: &Path) -> Result<Beatmap, anyhow::Error> {
     let contents = std::fs::read_to_string(path)?;
-    let parsed_map = osuparse::parse_beatmap(&contents)
+    parse_beatmap_from_str(&contents)
+}
+
+pub fn parse_beatmap_from_str(contents: &str) -> Result<Beatmap, anyhow::Error> {
+    let parsed_map = osuparse::parse_beatmap(contents)
         .map_err(|e| anyhow!("Failed to parse beatmap with osuparse: {:?}", e))?;
 
+    let difficulty = Difficulty {
+        circle_size: parsed_map.difficulty.circle_size,
+        // maps from before AR existed use OD for both
+        approach_rate: if contents.contains("ApproachRate:") {
+            parsed_map.difficulty.approach_rate
+        } else {
+            parsed_map.difficulty.overall_difficulty
+        },
+        overall_difficulty: parsed_map.difficulty.overall_difficulty,
+        hp_drain_rate: parsed_map.difficulty.hp_drain_rate,
+        slider_multiplier: parsed_map.difficulty.slider_multiplier,
+        slider_tick_rate: parsed_map.difficulty.slider_tick_rate,
+    };
+    let mut timing = build_timing(&parsed_map.timing_points);
+
+    let hit_objects: Vec<HitObject> = parsed_map
+        .hit_objects
+        .iter()
+        .map(|ho| {
+            let (x, y, start_time, end_time, obj_type, curve_points, slider) = match ho {
+                osuparse::HitObject::HitCircle(obj) => (
+                    obj.x,
+                    obj.y,
+                    obj.time as f32,
+                    obj.time as f32,
+                    HitObjectType::Circle,
+                    None,
+                    None,
+                ),
+                osuparse::HitObject::Slider(obj) => {
+                    let points: Vec<(f32, f32)> = obj
+                        .curve_points
+                        .iter()
+                        .map(|p| (p.0 as f32, p.1 as f32))
+                        .collect();
+                    let slider = build_slider(&timing, &difficulty, obj, &points);
+                    let start_time = obj.time as f32;
+                    let span_duration = obj.pixel_length / slider.velocity;
+                    (
+                        obj.x,
+                        obj.y,
+                        start_time,
+                        start_time + span_duration * slider.span_count as f32,
+                        HitObjectType::Slider,
+                        Some(points),
+                        Some(slider),
+                    )
+                }
+                osuparse::HitObject::Spinner(obj) => (
+                    256,
+                    192,
+                    obj.time as f32,
+                    obj.end_time as f32,
+                    HitObjectType::Spinner,
+                    None,
+                    None,
+                ),
+                osuparse::HitObject::HoldNote(obj) => (
+                    obj.x,
+                    obj.y,
+                    obj.time as f32,
+                    obj.end_time as f32,
+                    HitObjectType::HoldNote,
+                    None,
+                    None,
+                ),
+            };
+            HitObject {
+                x: x as f32,
+                y: y as f32,
+                start_time,
+                end_time,
+                obj_type,
+                curve_points,
+                slider,
+            }
+        })
+        .collect();
+
+    // the last kiai section runs until the end of the map
+    let map_end = hit_objects
+        .iter()
+        .map(|obj| obj.end_time)
+        .fold(0.0, f32::max);
+    timing.kiai_sections = kiai_sections(contents, map_end);
+
     let beatmap = Beatmap {
+        mode: match parsed_map.general.game_mode {
+            osuparse::GameMode::Osu => GameMode::Osu,
+            osuparse::GameMode::Taiko => GameMode::Taiko,
+            osuparse::GameMode::CTB => GameMode::Catch,
+            osuparse::GameMode::Mania => GameMode::Mania,
+        },
         title: parsed_map.metadata.title.clone(),
         artist: parsed_map.metadata.artist.clone(),
         difficulty_name: parsed_map.metadata.version.clone(),
         beatmap_id: parsed_map.metadata.beatmap_id,
         beatmapset_id: parsed_map.metadata.beatmap_set_id,
-        hit_objects: parsed_map
-            .hit_objects
-            .iter()
-            .map(|ho| {
-                let (x, y, start_time, _end_time, obj_type, curve_points) = match ho {
-                    osuparse::HitObject::HitCircle(obj) => (
-                        obj.x,
-                        obj.y,
-                        obj.time,
-                        obj.time,
-                        HitObjectType::Circle,
-                        None,
-                    ),
-                    osuparse::HitObject::Slider(obj) => {
-                        let points = obj
-                            .curve_points
-                            .iter()
-                            .map(|p| (p.0 as f32, p.1 as f32))
-                            .collect();
-                        (
-                            obj.x,
-                            obj.y,
-                            obj.time,
-                            calculate_slider_end_time(&parsed_map, obj),
-                            HitObjectType::Slider,
-                            Some(points),
-                        )
-                    }
-                    osuparse::HitObject::Spinner(obj) => (
-                        256,
-                        192,
-                        obj.time,
-                        obj.end_time,
-                        HitObjectType::Spinner,
-                        None,
-                    ),
-                    osuparse::HitObject::HoldNote(obj) => (
-                        obj.x,
-                        obj.y,
-                        obj.time,
-                        obj.end_time,
-                        HitObjectType::HoldNote,
-                        None,
-                    ),
-                };
-                HitObject {
-                    x: x as f32,
-                    y: y as f32,
-                    start_time: start_time as f32,
-                    obj_type,
-                    curve_points,
-                }
-            })
-            .collect(),
+        difficulty,
+        timing,
+        hit_objects,
     };
 
     Ok(beatmap)
 }
 
-fn calculate_slider_end_time(parsed_map: &osuparse::Beatmap, slider: &osuparse::Slider) -> i32 {
-    let slider_start_time = slider.time as f32;
+fn build_slider(
+    timing: &Timing,
+    difficulty: &Difficulty,
+    slider: &osuparse::Slider,
+    curve_points: &[(f32, f32)],
+) -> SliderData {
+    let curve_type = match slider.slider_type {
+        osuparse::SliderType::Linear => CurveType::Linear,
+        osuparse::SliderType::Bezier => CurveType::Bezier,
+        osuparse::SliderType::Perfect => CurveType::PerfectCircle,
+        osuparse::SliderType::Catmull => CurveType::Catmull,
+    };
+    let mut control_points = vec![(slider.x as f32, slider.y as f32)];
+    control_points.extend_from_slice(curve_points);
+
+    let start_time = slider.time as f32;
+    let span_count = slider.repeat.max(1) as u32;
+    let (velocity, tick_distance) = timing.slider_velocity_at(difficulty, start_time);
+    let path = SliderPath::new(curve_type, &control_points, slider.pixel_length);
+
+    SliderData {
+        curve_type,
+        span_count,
+        velocity,
+        end_position: path.end_position(span_count),
+        ticks: path.ticks(start_time, span_count, velocity, tick_distance),
+        path,
+    }
+}
 
-    // find red
-    let mut base_ms_per_beat = 1000.0;
-    for tp in parsed_map.timing_points.iter().rev() {
-        if !tp.inherited && tp.offset <= slider_start_time {
-            base_ms_per_beat = tp.ms_per_beat;
-            break;
+fn build_timing(timing_points: &[osuparse::TimingPoint]) -> Timing {
+    let mut timing = Timing::default();
+    // uninherited (red) lines have a positive beat length, inherited (green) ones
+    // a negative percentage
+    for tp in timing_points {
+        if tp.ms_per_beat > 0.0 {
+            timing.uninherited.push(UninheritedPoint {
+                time: tp.offset,
+                beat_length: tp.ms_per_beat,
+                meter: tp.meter.max(1) as u32,
+            });
+        } else if tp.ms_per_beat < 0.0 {
+            timing.inherited.push(InheritedPoint {
+                time: tp.offset,
+                velocity_multiplier: (-100.0 / tp.ms_per_beat).clamp(0.1, 10.0),
+            });
         }
     }
+    // stable, so points on the same time keep their file order
+    timing.uninherited.sort_by(|a, b| a.time.total_cmp(&b.time));
+    timing.inherited.sort_by(|a, b| a.time.total_cmp(&b.time));
+    timing
+}
+
+fn kiai_sections(contents: &str, map_end: f32) -> Vec<KiaiSection> {
+    let mut points = kiai_flags(contents);
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
 
-    // find green
-    let mut speed_multiplier = 1.0;
-    for tp in parsed_map.timing_points.iter().rev() {
-        if tp.offset <= slider_start_time {
-            if tp.inherited {
-                speed_multiplier = -100.0 / tp.ms_per_beat;
+    let mut sections = Vec::new();
+    let mut kiai_start: Option<f32> = None;
+    for (time, kiai) in points {
+        match (kiai_start, kiai) {
+            (None, true) => kiai_start = Some(time),
+            (Some(start), false) => {
+                sections.push(KiaiSection {
+                    start_time: start,
+                    end_time: time,
+                });
+                kiai_start = None;
             }
-            break;
+            _ => {}
         }
     }
+    if let Some(start) = kiai_start.filter(|&start| start < map_end) {
+        sections.push(KiaiSection {
+            start_time: start,
+            end_time: map_end,
+        });
+    }
+    sections
+}
 
-    let slider_velocity = parsed_map.difficulty.slider_multiplier as f32 * 100.0 * speed_multiplier;
-    let single_pass_duration = (slider.pixel_length / slider_velocity) * base_ms_per_beat;
-    let total_duration = single_pass_duration * slider.repeat as f32;
-
-    slider.time + total_duration as i32
+/// Time and kiai flag of every timing point. osuparse reads the whole effects
+/// field as a bool, which would count "omit first barline" (8) as kiai too, so
+/// the bit is read from the raw `[TimingPoints]` lines instead.
+fn kiai_flags(contents: &str) -> Vec<(f32, bool)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "[TimingPoints]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            let time = fields.next()?.parse::<f32>().ok()?;
+            // old maps don't have the effects field at all
+            let effects = fields
+                .nth(6)
+                .and_then(|e| e.parse::<u32>().ok())
+                .unwrap_or(0);
+            Some((time, effects & 1 != 0))
+        })
+        .collect()
 }
@@ -0,0 +1,124 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexingPhase {
+    Walk,
+    Parse,
+    Cluster,
+    Save,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProgressUpdate {
+    pub phase: IndexingPhase,
+    pub processed: u64,
+    /// 0 while the total isn't known yet (walking the Songs folder)
+    pub total: u64,
+    /// items per second since the phase started
+    pub throughput: f64,
+    pub eta_secs: Option<f64>,
+}
+
+/// Receives indexing progress. Updates are already throttled by the indexer,
+/// so implementations can forward them as is (Tauri events, progress bars...).
+pub trait ProgressSink: Send + Sync {
+    fn on_progress(&self, update: &ProgressUpdate);
+}
+
+struct PhaseState {
+    phase: IndexingPhase,
+    total: u64,
+    started: Instant,
+    last_emit: Instant,
+}
+
+/// Tracks the current phase and forwards at most one update per `EMIT_INTERVAL`
+/// to the sink. Safe to call `advance` from rayon workers.
+pub struct ProgressReporter<'a> {
+    sink: Option<&'a dyn ProgressSink>,
+    processed: AtomicU64,
+    state: Mutex<PhaseState>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new(sink: Option<&'a dyn ProgressSink>) -> Self {
+        let now = Instant::now();
+        Self {
+            sink,
+            processed: AtomicU64::new(0),
+            state: Mutex::new(PhaseState {
+                phase: IndexingPhase::Walk,
+                total: 0,
+                started: now,
+                last_emit: now,
+            }),
+        }
+    }
+
+    pub fn start_phase(&self, phase: IndexingPhase, total: u64) {
+        self.processed.store(0, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        *state = PhaseState {
+            phase,
+            total,
+            started: now,
+            last_emit: now,
+        };
+        self.emit(&state, 0);
+    }
+
+    pub fn advance(&self, count: u64) {
+        let processed = self.processed.fetch_add(count, Ordering::Relaxed) + count;
+        if self.sink.is_none() {
+            return;
+        }
+        // another worker is emitting right now, no need to wait for it
+        let Ok(mut state) = self.state.try_lock() else {
+            return;
+        };
+        if state.last_emit.elapsed() >= EMIT_INTERVAL {
+            state.last_emit = Instant::now();
+            self.emit(&state, processed);
+        }
+    }
+
+    pub fn finish_phase(&self) {
+        let state = self.state.lock().unwrap();
+        let processed = if state.total > 0 {
+            state.total
+        } else {
+            self.processed.load(Ordering::Relaxed)
+        };
+        self.emit(&state, processed);
+    }
+
+    fn emit(&self, state: &PhaseState, processed: u64) {
+        let Some(sink) = self.sink else {
+            return;
+        };
+
+        let elapsed = state.started.elapsed().as_secs_f64();
+        let throughput = if elapsed > 0.0 {
+            processed as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta_secs = (state.total > 0 && throughput > 0.0)
+            .then(|| state.total.saturating_sub(processed) as f64 / throughput);
+
+        sink.on_progress(&ProgressUpdate {
+            phase: state.phase,
+            processed,
+            total: state.total,
+            throughput,
+            eta_secs,
+        });
+    }
+}
@@ -1,92 +1,61 @@
 use super::model::MapInfo;
-use anyhow::{Context, Result};
 use seli_vector_db::{Vector, VectorDB};
-use std::path::Path;
+use serde::Deserialize;
+
+/// Optional bounds on the estimated difficulty of the results, all inclusive.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct SearchFilter {
+    pub min_stars: Option<f32>,
+    pub max_stars: Option<f32>,
+    pub min_aim: Option<f32>,
+    pub max_aim: Option<f32>,
+    pub min_speed: Option<f32>,
+    pub max_speed: Option<f32>,
+    pub min_combo: Option<u32>,
+    pub max_combo: Option<u32>,
+}
 
-pub fn load_map_infos(db_path: &Path) -> Result<Vec<MapInfo>> {
-    let paths_path = db_path.with_extension("paths.json");
-    serde_json::from_str(&std::fs::read_to_string(&paths_path).context(format!(
-        "Failed to load map paths from '{}'. Make sure it exists and 'index' was run.",
-        paths_path.display()
-    ))?)
-    .context("Failed to parse map paths JSON.")
+impl SearchFilter {
+    pub fn matches(&self, map_info: &MapInfo) -> bool {
+        fn within<T: PartialOrd + Copy>(value: T, min: Option<T>, max: Option<T>) -> bool {
+            min.map_or(true, |min| value >= min) && max.map_or(true, |max| value <= max)
+        }
+        let attributes = &map_info.attributes;
+        within(attributes.star_rating, self.min_stars, self.max_stars)
+            && within(attributes.aim, self.min_aim, self.max_aim)
+            && within(attributes.speed, self.min_speed, self.max_speed)
+            && within(attributes.max_combo, self.min_combo, self.max_combo)
+    }
 }
 
 pub fn perform_search<'a>(
     db: &'a VectorDB,
     map_infos: &'a [MapInfo],
     query_vector: &Vector,
-    query_map_index: usize,
+    exclude_index: Option<usize>,
     k: usize,
+    filter: &SearchFilter,
 ) -> Vec<(f32, &'a MapInfo)> {
-    let nprobe = (db.num_clusters().unwrap_or(1) as f64).sqrt() as usize + 1;
+    let num_clusters = db.num_clusters().unwrap_or(1);
+    let mut nprobe = (num_clusters as f64).sqrt() as usize + 1;
     const SEARCH_BUFFER: usize = 1;
-    let results = db.search(&query_vector, k + SEARCH_BUFFER, nprobe);
-
-    results
-        .iter()
-        .filter(|r| r.id != query_map_index)
-        .take(k)
-        .map(|r| (r.score, &map_infos[r.id]))
-        .collect()
-}
-
-/*
-/// old CLI-based search function.
-pub fn run_search(query_map_path: &PathBuf, db_path: &PathBuf, k: usize) -> Result<()> {
-    println!("Loading database from '{}'...", db_path.display());
-    let db = VectorDB::load_from_file(db_path)
-        .context("Failed to load database. Did you run the 'index' command first?")?;
-
-    let map_infos = load_map_infos(db_path)?;
-
-    println!("Analyzing query map: '{}'", query_map_path.display());
-    let beatmap = parser::parse_beatmap_from_file(query_map_path)?;
-    let query_vector = features::extract_features(&beatmap)
-        .context("Could not extract features from the query map.")?;
 
-    println!("\nSearching for the top {} similar maps...", k);
-
-    let search_results = perform_search(&db, &map_infos, &query_vector, 0, k); // Note: query_map_index is simplified here
-
-    if search_results.is_empty() {
-        println!("No similar maps found.");
-    } else {
-        println!("--- Search Results ---");
-        search_results
+    // filtered out results leave gaps, keep asking for more (and looking in more
+    // clusters, the probed ones may not have enough matches) until k are left
+    let mut candidates = k + SEARCH_BUFFER;
+    loop {
+        let results: Vec<(f32, &MapInfo)> = db
+            .search(query_vector, candidates, nprobe)
             .iter()
-            .enumerate()
-            .for_each(|(i, (score, map_info))| {
-                let map_display = format!(
-                    "{} - {} [{}]",
-                    map_info.artist, map_info.title, map_info.difficulty_name
-                );
-
-                let divergence = (1.0 - score) * 100.0;
-                let formatted_divergence = format!("{:.2}", divergence);
-
-                let (label, colored_divergence) = if divergence <= 0.5 {
-                    ("Close", formatted_divergence.green())
-                } else if divergence <= 1.5 {
-                    ("Similar", formatted_divergence.yellow())
-                } else if divergence <= 3.0 {
-                    ("Related", formatted_divergence.bright_yellow())
-                } else {
-                    ("Distant", formatted_divergence.red())
-                };
-
-                println!(
-                    "{}. Score: {:.4} (Divergence: {}, {}) | {} (https://osu.ppy.sh/beatmapsets/{}#osu/{})",
-                    (i + 1).to_string().dimmed(),
-                    score,
-                    colored_divergence,
-                    label,
-                    map_display,
-                    map_info.beatmapset_id, map_info.beatmap_id
-                );
-            });
+            .filter(|r| Some(r.id) != exclude_index && filter.matches(&map_infos[r.id]))
+            .take(k)
+            .map(|r| (r.score, &map_infos[r.id]))
+            .collect();
+        let searched_everything = candidates >= db.len() && nprobe >= num_clusters;
+        if results.len() >= k || searched_everything {
+            return results;
+        }
+        candidates = (candidates * 4).min(db.len());
+        nprobe = (nprobe * 2).min(num_clusters);
     }
-
-    Ok(())
 }
-*/
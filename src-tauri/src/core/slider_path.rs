@@ -0,0 +1,335 @@
+use super::model::CurveType;
+
+type Point = (f32, f32);
+
+// how far a bezier/arc approximation may stray from the real curve, in osu!pixels
+const CURVE_TOLERANCE: f32 = 0.25;
+const CATMULL_DETAIL: usize = 50;
+const MAX_CURVE_SAMPLES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SliderTick {
+    pub time: f32,
+    pub position: Point,
+}
+
+/// A slider body flattened into a polyline, cut or extended to the slider's
+/// pixel length like the game does.
+#[derive(Debug, Clone, Default)]
+pub struct SliderPath {
+    points: Vec<Point>,
+    /// distance along the path up to each point
+    cumulative_lengths: Vec<f32>,
+}
+
+impl SliderPath {
+    /// `control_points` includes the slider head. A `pixel_length` of 0 (or
+    /// less) keeps the calculated length.
+    pub fn new(curve_type: CurveType, control_points: &[Point], pixel_length: f32) -> Self {
+        let mut points = match control_points {
+            [] => Vec::new(),
+            [head] => vec![*head],
+            _ => flatten(curve_type, control_points),
+        };
+        points.dedup();
+
+        let mut path = SliderPath {
+            cumulative_lengths: cumulative_lengths(&points),
+            points,
+        };
+        if pixel_length > 0.0 {
+            path.fit_to_length(pixel_length);
+        }
+        path
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn length(&self) -> f32 {
+        self.cumulative_lengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// Position at `progress` (0 = head, 1 = tail) along a single span.
+    pub fn position_at(&self, progress: f32) -> Point {
+        self.position_at_distance(progress.clamp(0.0, 1.0) * self.length())
+    }
+
+    pub fn position_at_distance(&self, distance: f32) -> Point {
+        let Some(&first) = self.points.first() else {
+            return (0.0, 0.0);
+        };
+        let index = self
+            .cumulative_lengths
+            .partition_point(|&length| length < distance);
+        if index == 0 {
+            return first;
+        }
+        if index >= self.points.len() {
+            return *self.points.last().unwrap();
+        }
+
+        let (start, end) = (self.points[index - 1], self.points[index]);
+        let segment_start = self.cumulative_lengths[index - 1];
+        let segment_length = self.cumulative_lengths[index] - segment_start;
+        if segment_length <= 0.0 {
+            return end;
+        }
+        lerp(start, end, (distance - segment_start) / segment_length)
+    }
+
+    /// Where the slider ends after all its spans (odd span counts end on the tail).
+    pub fn end_position(&self, span_count: u32) -> Point {
+        if span_count % 2 == 0 {
+            self.position_at(0.0)
+        } else {
+            self.position_at(1.0)
+        }
+    }
+
+    /// Slider ticks of every span. `velocity` is in osu!pixels per ms.
+    pub fn ticks(
+        &self,
+        start_time: f32,
+        span_count: u32,
+        velocity: f32,
+        tick_distance: f32,
+    ) -> Vec<SliderTick> {
+        let length = self.length();
+        if tick_distance <= 0.0 || velocity <= 0.0 || length <= 0.0 {
+            return Vec::new();
+        }
+
+        // the game drops ticks that would land right before the span end
+        let min_distance_from_end = velocity * 10.0;
+        let mut span_distances = Vec::new();
+        let mut distance = tick_distance;
+        while distance < length - min_distance_from_end && span_distances.len() < MAX_CURVE_SAMPLES
+        {
+            span_distances.push(distance);
+            distance += tick_distance;
+        }
+
+        let span_duration = length / velocity;
+        let mut ticks = Vec::with_capacity(span_distances.len() * span_count as usize);
+        for span in 0..span_count {
+            let span_start = start_time + span as f32 * span_duration;
+            let reversed = span % 2 == 1;
+            for &distance in &span_distances {
+                let time_distance = if reversed {
+                    length - distance
+                } else {
+                    distance
+                };
+                ticks.push(SliderTick {
+                    time: span_start + time_distance / velocity,
+                    position: self.position_at_distance(distance),
+                });
+            }
+            if reversed {
+                let span_ticks = ticks.len() - span_distances.len();
+                ticks[span_ticks..].reverse();
+            }
+        }
+        ticks
+    }
+
+    fn fit_to_length(&mut self, pixel_length: f32) {
+        if self.points.len() < 2 {
+            return;
+        }
+
+        let calculated_length = self.length();
+        if calculated_length > pixel_length {
+            // cut the path where it reaches pixel_length
+            let end = self.position_at_distance(pixel_length);
+            let keep = self
+                .cumulative_lengths
+                .partition_point(|&length| length < pixel_length);
+            self.points.truncate(keep);
+            self.points.push(end);
+        } else if calculated_length < pixel_length {
+            // extend the last segment in its direction
+            let count = self.points.len();
+            let (from, to) = (self.points[count - 2], self.points[count - 1]);
+            let segment_length = distance(from, to);
+            if segment_length > 0.0 {
+                let extra = pixel_length - calculated_length;
+                let end = (
+                    to.0 + (to.0 - from.0) / segment_length * extra,
+                    to.1 + (to.1 - from.1) / segment_length * extra,
+                );
+                self.points[count - 1] = end;
+            }
+        }
+        self.cumulative_lengths = cumulative_lengths(&self.points);
+    }
+}
+
+fn flatten(curve_type: CurveType, control_points: &[Point]) -> Vec<Point> {
+    match curve_type {
+        CurveType::Linear => control_points.to_vec(),
+        CurveType::Bezier => bezier(control_points),
+        CurveType::Catmull => catmull(control_points),
+        CurveType::PerfectCircle => match control_points {
+            [a, b, c] => circular_arc(*a, *b, *c).unwrap_or_else(|| bezier(control_points)),
+            _ => bezier(control_points),
+        },
+    }
+}
+
+/// Red anchors (a repeated control point) split the curve into separate beziers.
+fn bezier(control_points: &[Point]) -> Vec<Point> {
+    let mut points = Vec::new();
+    let mut segment_start = 0;
+    for i in 1..=control_points.len() {
+        let is_segment_end =
+            i == control_points.len() || control_points[i] == control_points[i - 1];
+        if !is_segment_end {
+            continue;
+        }
+
+        let segment = &control_points[segment_start..i];
+        match segment {
+            [] => {}
+            [point] => points.push(*point),
+            [start, end] => points.extend([*start, *end]),
+            _ => points.extend(bezier_segment(segment)),
+        }
+        segment_start = i;
+    }
+    points
+}
+
+fn bezier_segment(control_points: &[Point]) -> Vec<Point> {
+    let polygon_length: f32 = control_points
+        .windows(2)
+        .map(|pair| distance(pair[0], pair[1]))
+        .sum();
+    let steps =
+        ((polygon_length / (CURVE_TOLERANCE * 16.0)).ceil() as usize).clamp(2, MAX_CURVE_SAMPLES);
+
+    let mut scratch = control_points.to_vec();
+    (0..=steps)
+        .map(|step| {
+            let t = step as f32 / steps as f32;
+            scratch.copy_from_slice(control_points);
+            // de casteljau
+            for level in (1..scratch.len()).rev() {
+                for i in 0..level {
+                    scratch[i] = lerp(scratch[i], scratch[i + 1], t);
+                }
+            }
+            scratch[0]
+        })
+        .collect()
+}
+
+/// `None` when the points are (almost) on a line, the game then draws a bezier.
+fn circular_arc(a: Point, b: Point, c: Point) -> Option<Vec<Point>> {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d.abs() < 1e-3 {
+        return None;
+    }
+
+    let a_sq = a.0 * a.0 + a.1 * a.1;
+    let b_sq = b.0 * b.0 + b.1 * b.1;
+    let c_sq = c.0 * c.0 + c.1 * c.1;
+    let center = (
+        (a_sq * (b.1 - c.1) + b_sq * (c.1 - a.1) + c_sq * (a.1 - b.1)) / d,
+        (a_sq * (c.0 - b.0) + b_sq * (a.0 - c.0) + c_sq * (b.0 - a.0)) / d,
+    );
+    let radius = distance(a, center);
+
+    let start_angle = (a.1 - center.1).atan2(a.0 - center.0);
+    let mut end_angle = (c.1 - center.1).atan2(c.0 - center.0);
+    while end_angle < start_angle {
+        end_angle += std::f32::consts::TAU;
+    }
+
+    // go the other way around if b isn't on the counter-clockwise arc
+    let mut sweep = end_angle - start_angle;
+    let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+    if cross < 0.0 {
+        sweep -= std::f32::consts::TAU;
+    }
+
+    let steps = if 2.0 * radius <= CURVE_TOLERANCE {
+        2
+    } else {
+        let max_step_angle = 2.0 * (1.0 - CURVE_TOLERANCE / radius).clamp(-1.0, 1.0).acos();
+        ((sweep.abs() / max_step_angle).ceil() as usize).clamp(2, MAX_CURVE_SAMPLES)
+    };
+
+    Some(
+        (0..=steps)
+            .map(|step| {
+                let angle = start_angle + sweep * step as f32 / steps as f32;
+                (
+                    center.0 + radius * angle.cos(),
+                    center.1 + radius * angle.sin(),
+                )
+            })
+            .collect(),
+    )
+}
+
+// only old (pre-2013) maps still use these
+fn catmull(control_points: &[Point]) -> Vec<Point> {
+    let count = control_points.len();
+    let mut points = Vec::with_capacity(count * CATMULL_DETAIL);
+    for i in 0..count - 1 {
+        let v1 = if i > 0 {
+            control_points[i - 1]
+        } else {
+            control_points[i]
+        };
+        let v2 = control_points[i];
+        let v3 = control_points[i + 1];
+        let v4 = if i + 2 < count {
+            control_points[i + 2]
+        } else {
+            (2.0 * v3.0 - v2.0, 2.0 * v3.1 - v2.1)
+        };
+
+        for step in 0..CATMULL_DETAIL {
+            let t = step as f32 / CATMULL_DETAIL as f32;
+            points.push(catmull_point(v1, v2, v3, v4, t));
+        }
+    }
+    points.push(control_points[count - 1]);
+    points
+}
+
+fn catmull_point(v1: Point, v2: Point, v3: Point, v4: Point, t: f32) -> Point {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let axis = |p1: f32, p2: f32, p3: f32, p4: f32| {
+        0.5 * (2.0 * p2
+            + (-p1 + p3) * t
+            + (2.0 * p1 - 5.0 * p2 + 4.0 * p3 - p4) * t2
+            + (-p1 + 3.0 * p2 - 3.0 * p3 + p4) * t3)
+    };
+    (axis(v1.0, v2.0, v3.0, v4.0), axis(v1.1, v2.1, v3.1, v4.1))
+}
+
+fn cumulative_lengths(points: &[Point]) -> Vec<f32> {
+    let mut total = 0.0;
+    let mut lengths = Vec::with_capacity(points.len());
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 {
+            total += distance(points[i - 1], *point);
+        }
+        lengths.push(total);
+    }
+    lengths
+}
+
+fn lerp(a: Point, b: Point, t: f32) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
@@ -0,0 +1,205 @@
+use super::difficulty::DIFFICULTY_VERSION;
+use super::features::{self, FEATURE_COUNT, FEATURE_SCHEMA_VERSION, WEIGHTS};
+use super::model::MapInfo;
+use anyhow::{bail, Context, Result};
+use seli_vector_db::{Vector, VectorDB};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bump whenever the layout of the file (or of `MapInfo`) changes.
+pub const FORMAT_VERSION: u32 = 7;
+
+const MAGIC: &[u8; 8] = b"OSUXIDX\0";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexHeader {
+    pub format_version: u32,
+    pub feature_schema_version: u32,
+    pub feature_count: u32,
+    pub weights: Vec<f32>,
+    /// `features::schema_fingerprint` of the build that wrote the file
+    pub feature_fingerprint: String,
+    /// `difficulty::DIFFICULTY_VERSION` the stored attributes were estimated with
+    pub difficulty_version: u32,
+    /// unix seconds
+    pub built_at: u64,
+}
+
+impl IndexHeader {
+    fn current() -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            feature_schema_version: FEATURE_SCHEMA_VERSION,
+            feature_count: FEATURE_COUNT as u32,
+            weights: WEIGHTS.to_vec(),
+            feature_fingerprint: features::schema_fingerprint(),
+            difficulty_version: DIFFICULTY_VERSION,
+            built_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+        }
+    }
+
+    /// Whether the vectors in this file are comparable with what
+    /// `features::extract_features` produces right now.
+    pub fn check_compatible(&self) -> Result<(), IncompatibleIndex> {
+        if self.format_version != FORMAT_VERSION {
+            return Err(IncompatibleIndex::Format {
+                found: self.format_version,
+            });
+        }
+        if self.feature_schema_version != FEATURE_SCHEMA_VERSION {
+            return Err(IncompatibleIndex::FeatureSchema {
+                found: self.feature_schema_version,
+            });
+        }
+        if self.feature_fingerprint != features::schema_fingerprint() {
+            return Err(IncompatibleIndex::FeatureWeights);
+        }
+        if self.difficulty_version != DIFFICULTY_VERSION {
+            return Err(IncompatibleIndex::Difficulty {
+                found: self.difficulty_version,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The index was written by another version of the app and has to be rebuilt.
+/// Returned inside the `anyhow::Error` of `load`, so callers can downcast to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncompatibleIndex {
+    Format {
+        found: u32,
+    },
+    FeatureSchema {
+        found: u32,
+    },
+    /// same schema version, but the feature count or weights differ
+    FeatureWeights,
+    Difficulty {
+        found: u32,
+    },
+}
+
+impl fmt::Display for IncompatibleIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncompatibleIndex::Format { found } => write!(
+                f,
+                "index file format v{} is not supported (expected v{}), re-index your maps",
+                found, FORMAT_VERSION
+            ),
+            IncompatibleIndex::FeatureSchema { found } => write!(
+                f,
+                "index was built by another feature extractor (schema v{}, current v{}), re-index your maps",
+                found, FEATURE_SCHEMA_VERSION
+            ),
+            IncompatibleIndex::FeatureWeights => write!(
+                f,
+                "index was built with different feature weights (schema v{}), re-index your maps",
+                FEATURE_SCHEMA_VERSION
+            ),
+            IncompatibleIndex::Difficulty { found } => write!(
+                f,
+                "star ratings were estimated by another version (v{}, current v{}), re-index your maps",
+                found, DIFFICULTY_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IncompatibleIndex {}
+
+/// Everything search needs, stored together in one file so it can't get out of sync.
+pub struct IndexFile {
+    pub header: IndexHeader,
+    pub db: VectorDB,
+    pub map_infos: Vec<MapInfo>,
+    /// raw (not normalized) feature vectors, same order as `map_infos`
+    pub vectors: Vec<Vector>,
+}
+
+// layout: MAGIC | header | map infos | vectors | VectorDB
+pub fn save(path: &Path, db: &VectorDB, map_infos: &[MapInfo], vectors: &[Vector]) -> Result<()> {
+    let config = bincode::config::standard();
+    let tmp_path = path.with_extension("db.tmp");
+    {
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create '{}'", tmp_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC)?;
+        bincode::serde::encode_into_std_write(IndexHeader::current(), &mut writer, config)?;
+        bincode::serde::encode_into_std_write(map_infos, &mut writer, config)?;
+        bincode::serde::encode_into_std_write(vectors, &mut writer, config)?;
+        bincode::encode_into_std_write(db, &mut writer, config)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+    }
+    // readers either see the old file or the complete new one
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace '{}'", path.display()))?;
+    Ok(())
+}
+
+/// Loads the index, failing with `IncompatibleIndex` if it has to be rebuilt.
+pub fn load(path: &Path) -> Result<IndexFile> {
+    let config = bincode::config::standard();
+    let mut reader = open(path)?;
+    let header = read_header_from(&mut reader)?;
+    header.check_compatible()?;
+
+    let map_infos: Vec<MapInfo> = bincode::serde::decode_from_std_read(&mut reader, config)
+        .context("Failed to read map infos.")?;
+    let vectors: Vec<Vector> = bincode::serde::decode_from_std_read(&mut reader, config)
+        .context("Failed to read feature vectors.")?;
+    let db: VectorDB = bincode::decode_from_std_read(&mut reader, config)
+        .context("Failed to read search index.")?;
+    if map_infos.len() != vectors.len() || db.len() != vectors.len() {
+        bail!(
+            "Index file '{}' is corrupted, re-index your maps.",
+            path.display()
+        );
+    }
+
+    Ok(IndexFile {
+        header,
+        db,
+        map_infos,
+        vectors,
+    })
+}
+
+/// Reads only the header. Only the format version is checked, not the features.
+pub fn read_header(path: &Path) -> Result<IndexHeader> {
+    read_header_from(&mut open(path)?)
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open index file '{}'", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+fn read_header_from(reader: &mut BufReader<File>) -> Result<IndexHeader> {
+    let mut magic = [0u8; MAGIC.len()];
+    // databases from before the container format are a bare VectorDB
+    if reader.read_exact(&mut magic).is_err() || &magic != MAGIC {
+        return Err(IncompatibleIndex::Format { found: 0 }.into());
+    }
+    let config = bincode::config::standard();
+    // the version is the header's first field, check it before the rest whose layout may differ
+    let (format_version, _): (u32, usize) = bincode::decode_from_slice(reader.fill_buf()?, config)
+        .context("Failed to read index header.")?;
+    if format_version != FORMAT_VERSION {
+        return Err(IncompatibleIndex::Format {
+            found: format_version,
+        }
+        .into());
+    }
+    bincode::serde::decode_from_std_read(reader, config).context("Failed to read index header.")
+}
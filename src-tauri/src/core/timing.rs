@@ -0,0 +1,223 @@
+use super::model::{Difficulty, HitObject};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// what the game uses when a map has no red line at all
+const DEFAULT_BEAT_LENGTH: f32 = 1000.0;
+
+/// Red line, sets the tempo and meter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UninheritedPoint {
+    pub time: f32,
+    /// ms per beat
+    pub beat_length: f32,
+    /// beats per measure
+    pub meter: u32,
+}
+
+impl UninheritedPoint {
+    pub fn bpm(&self) -> f32 {
+        60_000.0 / self.beat_length
+    }
+}
+
+/// Green line, changes the slider velocity until the next timing point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InheritedPoint {
+    pub time: f32,
+    /// multiplier on the difficulty's slider multiplier, 0.1..10
+    pub velocity_multiplier: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KiaiSection {
+    pub start_time: f32,
+    pub end_time: f32,
+}
+
+/// All timing points of a map, sorted by time.
+#[derive(Debug, Clone, Default)]
+pub struct Timing {
+    pub uninherited: Vec<UninheritedPoint>,
+    pub inherited: Vec<InheritedPoint>,
+    pub kiai_sections: Vec<KiaiSection>,
+}
+
+/// What search results and the index keep of a map's timing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct TimingSummary {
+    /// the BPM that lasts the longest
+    pub bpm: f32,
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+    /// beats per measure of the first red line
+    pub meter: u32,
+    pub bpm_changes: u32,
+    pub velocity_changes: u32,
+    /// share of the map's drain time spent in kiai, 0..1
+    pub kiai_ratio: f32,
+    /// objects per second in kiai over objects per second overall, 0 without kiai
+    pub kiai_density: f32,
+}
+
+impl Timing {
+    /// The red line active at `time`. Objects before the first red line use that one.
+    pub fn uninherited_at(&self, time: f32) -> Option<&UninheritedPoint> {
+        let index = self.uninherited.partition_point(|tp| tp.time <= time);
+        self.uninherited.get(index.saturating_sub(1))
+    }
+
+    pub fn beat_length_at(&self, time: f32) -> f32 {
+        self.uninherited_at(time)
+            .map_or(DEFAULT_BEAT_LENGTH, |tp| tp.beat_length)
+    }
+
+    pub fn meter_at(&self, time: f32) -> u32 {
+        self.uninherited_at(time).map_or(4, |tp| tp.meter)
+    }
+
+    /// Green line multiplier at `time`. A red line resets it to 1.
+    pub fn velocity_multiplier_at(&self, time: f32) -> f32 {
+        let index = self.inherited.partition_point(|tp| tp.time <= time);
+        let Some(green) = index.checked_sub(1).map(|i| &self.inherited[i]) else {
+            return 1.0;
+        };
+        let red_index = self.uninherited.partition_point(|tp| tp.time <= time);
+        let red_time = red_index
+            .checked_sub(1)
+            .map_or(f32::NEG_INFINITY, |i| self.uninherited[i].time);
+        // a green line on the same time as a red one still applies
+        if green.time >= red_time {
+            green.velocity_multiplier
+        } else {
+            1.0
+        }
+    }
+
+    /// Slider velocity in osu!pixels per ms and the distance between slider ticks at `time`.
+    pub fn slider_velocity_at(&self, difficulty: &Difficulty, time: f32) -> (f32, f32) {
+        let pixels_per_beat =
+            difficulty.slider_multiplier * 100.0 * self.velocity_multiplier_at(time);
+        let tick_distance = pixels_per_beat / difficulty.slider_tick_rate.max(0.1);
+        (pixels_per_beat / self.beat_length_at(time), tick_distance)
+    }
+
+    pub fn is_kiai(&self, time: f32) -> bool {
+        self.kiai_sections
+            .iter()
+            .any(|section| section.start_time <= time && time < section.end_time)
+    }
+
+    /// The BPM that lasts the longest up to `end_time`, like the song select shows.
+    pub fn dominant_bpm(&self, end_time: f32) -> f32 {
+        // keyed by the rounded beat length so 333.33 and 333.334 count as the same tempo
+        let mut durations: HashMap<u32, (f32, f32)> = HashMap::new();
+        for (i, tp) in self.uninherited.iter().enumerate() {
+            if tp.time > end_time {
+                break;
+            }
+            let next_time = self
+                .uninherited
+                .get(i + 1)
+                .map_or(end_time, |next| next.time.min(end_time));
+            let (_, duration) = durations
+                .entry(tp.beat_length.round() as u32)
+                .or_insert((tp.beat_length, 0.0));
+            *duration += (next_time - tp.time).max(0.0);
+        }
+        let beat_length = durations
+            .into_iter()
+            .max_by(|(a_key, a), (b_key, b)| a.1.total_cmp(&b.1).then(b_key.cmp(a_key)))
+            .map_or_else(
+                || self.beat_length_at(0.0),
+                |(_, (beat_length, _))| beat_length,
+            );
+        60_000.0 / beat_length.max(1.0)
+    }
+
+    pub fn bpm_range(&self) -> (f32, f32) {
+        let bpms = self.uninherited.iter().map(UninheritedPoint::bpm);
+        let min = bpms.clone().fold(f32::INFINITY, f32::min);
+        let max = bpms.fold(0.0, f32::max);
+        if min.is_finite() {
+            (min, max)
+        } else {
+            let bpm = 60_000.0 / DEFAULT_BEAT_LENGTH;
+            (bpm, bpm)
+        }
+    }
+
+    /// Red lines that actually change the tempo (not just re-snap or change meter).
+    pub fn bpm_change_count(&self) -> u32 {
+        self.uninherited
+            .windows(2)
+            .filter(|pair| (pair[0].bpm() - pair[1].bpm()).abs() >= 1.0)
+            .count() as u32
+    }
+
+    /// Timing points after which sliders move at a different speed.
+    pub fn velocity_change_count(&self) -> u32 {
+        // red lines reset the multiplier, on the same time the green line wins
+        let mut multipliers: Vec<(f32, f32)> = self
+            .uninherited
+            .iter()
+            .map(|tp| (tp.time, 1.0))
+            .chain(
+                self.inherited
+                    .iter()
+                    .map(|tp| (tp.time, tp.velocity_multiplier)),
+            )
+            .collect();
+        multipliers.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut previous = 1.0;
+        let mut changes = 0;
+        for (_, multiplier) in multipliers {
+            if (multiplier - previous).abs() > 0.01 {
+                changes += 1;
+            }
+            previous = multiplier;
+        }
+        changes
+    }
+
+    pub fn summary(&self, hit_objects: &[HitObject]) -> TimingSummary {
+        let start_time = hit_objects.first().map_or(0.0, |obj| obj.start_time);
+        let end_time = hit_objects
+            .iter()
+            .map(|obj| obj.end_time)
+            .fold(start_time, f32::max);
+        let (min_bpm, max_bpm) = self.bpm_range();
+        let drain_time = end_time - start_time;
+        let kiai_time: f32 = self
+            .kiai_sections
+            .iter()
+            .map(|section| {
+                (section.end_time.min(end_time) - section.start_time.max(start_time)).max(0.0)
+            })
+            .sum();
+        let kiai_objects = hit_objects
+            .iter()
+            .filter(|obj| self.is_kiai(obj.start_time))
+            .count();
+        let kiai_density = if kiai_time > 0.0 && drain_time > 0.0 {
+            (kiai_objects as f32 / kiai_time) / (hit_objects.len() as f32 / drain_time)
+        } else {
+            0.0
+        };
+        TimingSummary {
+            bpm: self.dominant_bpm(end_time),
+            min_bpm,
+            max_bpm,
+            meter: self.meter_at(start_time),
+            bpm_changes: self.bpm_change_count(),
+            velocity_changes: self.velocity_change_count(),
+            kiai_ratio: if drain_time > 0.0 {
+                (kiai_time / drain_time).min(1.0)
+            } else {
+                0.0
+            },
+            kiai_density,
+        }
+    }
+}
@@ -4,8 +4,10 @@ use serde::Serialize;
 #[serde(tag = "type", content = "payload")]
 pub enum AppError {
     DatabaseNotIndexed,
+    /// the index was built by another version of the feature extractor
+    IndexOutdated(String),
     MapNotFound(i32),
     FeatureExtractionFailed,
-    IoError(String),
+    InvalidBeatmap(String),
     ConfigError(String),
 }
@@ -1,21 +1,61 @@
 mod commands;
-mod core;
+pub mod core;
 mod error;
+mod watcher;
 
-use crate::core::model::{AppConfig, IndexingProgress, MapInfo};
-use seli_vector_db::VectorDB;
+use crate::core::{
+    model::{AppConfig, MapInfo},
+    storage::IncompatibleIndex,
+};
+use seli_vector_db::{Vector, VectorDB};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64},
+    Arc, Mutex,
+};
 use tauri::{Emitter, Listener, Manager};
+
+/// Everything a search reads, swapped as a whole so a search never sees
+/// a db from one index and map infos from another.
+pub struct LoadedIndex {
+    pub db: VectorDB,
+    pub map_infos: Vec<MapInfo>,
+    /// raw feature vectors, same order as `map_infos`
+    pub vectors: Vec<Vector>,
+    pub map_id_to_index: HashMap<i32, usize>,
+}
+
+impl LoadedIndex {
+    pub fn new(db: VectorDB, map_infos: Vec<MapInfo>, vectors: Vec<Vector>) -> Self {
+        let map_id_to_index = map_infos
+            .iter()
+            .enumerate()
+            .map(|(index, info)| (info.beatmap_id, index))
+            .collect();
+        Self {
+            db,
+            map_infos,
+            vectors,
+            map_id_to_index,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct AppState {
-    pub db: Arc<Mutex<Option<VectorDB>>>,
-    pub map_infos: Arc<Mutex<Option<Vec<MapInfo>>>>,
-    pub map_id_to_index: Arc<Mutex<HashMap<i32, usize>>>,
-    pub db_path: Arc<Mutex<PathBuf>>,
+    pub index: Arc<Mutex<Option<LoadedIndex>>>,
+    /// never changes after startup
+    pub db_path: PathBuf,
+    /// held while the index on disk is rebuilt or updated, so runs don't interleave
+    pub indexing_lock: Arc<Mutex<()>>,
+    /// bumped (under `indexing_lock`) whenever the index on disk is replaced
+    pub index_generation: Arc<AtomicU64>,
     pub config: Arc<Mutex<AppConfig>>,
-    pub indexing_progress: Arc<Mutex<IndexingProgress>>,
+    pub indexing_cancel_flag: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+    pub watcher: Arc<Mutex<Option<watcher::SongsWatcher>>>,
+    /// why the index on disk couldn't be used, if it has to be rebuilt
+    pub index_outdated: Arc<Mutex<Option<String>>>,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -25,38 +65,33 @@ pub fn run() {
     let data_dir = core::config::data_dir().expect("Failed to create data directory");
     let db_path = data_dir.join("osu_maps.db");
 
-    let db = VectorDB::load_from_file(&db_path).ok();
-    let map_infos = if db.is_some() {
-        core::searcher::load_map_infos(&db_path).ok()
-    } else {
-        None
+    let state = AppState {
+        db_path: db_path.clone(),
+        config: Arc::new(Mutex::new(config)),
+        ..Default::default()
     };
-
-    let mut map_id_to_index = HashMap::new();
-    if let Some(infos) = &map_infos {
-        for (index, info) in infos.iter().enumerate() {
-            map_id_to_index.insert(info.beatmap_id, index);
-        }
-    }
+    load_index(&state, &db_path);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
-        .manage(AppState {
-            db: Arc::new(Mutex::new(db)),
-            map_infos: Arc::new(Mutex::new(map_infos)),
-            map_id_to_index: Arc::new(Mutex::new(map_id_to_index)),
-            db_path: Arc::new(Mutex::new(db_path)),
-            config: Arc::new(Mutex::new(config)),
-            indexing_progress: Arc::new(Mutex::new(IndexingProgress::default())),
-        })
+        .manage(state)
         .invoke_handler(tauri::generate_handler![
             commands::get_config,
             commands::search,
+            commands::search_by_osu,
             commands::index,
-            commands::get_indexing_status,
+            commands::cancel_indexing,
+            commands::get_indexing_report,
             commands::is_db_indexed
         ])
         .setup(|app| -> Result<(), Box<dyn std::error::Error>> {
+            let state: tauri::State<AppState> = app.state();
+            let songs_path = state.config.lock().unwrap().songs_path.clone();
+            let is_indexed = state.index.lock().unwrap().is_some();
+            if let (Some(songs_path), true) = (songs_path, is_indexed) {
+                watcher::restart(app.handle(), &songs_path);
+            }
+
             let app_handle = app.handle().clone();
             app.listen("index-complete", move |_event| {
                 let success = reload_app_state(&app_handle);
@@ -73,26 +108,32 @@ pub fn run() {
 fn reload_app_state(app_handle: &tauri::AppHandle) -> bool {
     println!("Received index-complete event. Reloading state...");
     let state: tauri::State<AppState> = app_handle.state();
-    let db_path = state.db_path.lock().unwrap().clone();
-
-    let new_db = VectorDB::load_from_file(&db_path).ok();
-    let new_map_infos = if new_db.is_some() {
-        core::searcher::load_map_infos(&db_path).ok()
-    } else {
-        None
-    };
-    let mut new_map_id_to_index = HashMap::new();
-    if let Some(infos) = &new_map_infos {
-        for (index, info) in infos.iter().enumerate() {
-            new_map_id_to_index.insert(info.beatmap_id, index);
-        }
-    }
-
-    *state.db.lock().unwrap() = new_db;
-    *state.map_infos.lock().unwrap() = new_map_infos;
-    *state.map_id_to_index.lock().unwrap() = new_map_id_to_index;
+    load_index(&state, &state.db_path);
     println!("State reloaded successfully!");
 
-    let is_indexed = state.db.lock().unwrap().is_some();
+    let is_indexed = state.index.lock().unwrap().is_some();
     is_indexed
 }
+
+/// Loads the index from disk into `state`. An index built by another version
+/// of the feature extractor is left unloaded and flagged in `index_outdated`,
+/// so the frontend can offer a rebuild instead of returning bad similarities.
+fn load_index(state: &AppState, db_path: &Path) {
+    let mut index_outdated = None;
+    match core::storage::load(db_path) {
+        Ok(index) => {
+            *state.index.lock().unwrap() =
+                Some(LoadedIndex::new(index.db, index.map_infos, index.vectors));
+        }
+        Err(e) => {
+            if let Some(incompatible) = e.downcast_ref::<IncompatibleIndex>() {
+                index_outdated = Some(incompatible.to_string());
+            }
+            if db_path.exists() {
+                eprintln!("Failed to load index: {:#}", e);
+            }
+            *state.index.lock().unwrap() = None;
+        }
+    }
+    *state.index_outdated.lock().unwrap() = index_outdated;
+}
@@ -0,0 +1,168 @@
+use crate::core::{self, model::ManifestEntry};
+use crate::AppState;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::Ordering,
+    mpsc::{self, RecvTimeoutError},
+};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+// osu! extracts a whole .osz at once, wait for it to settle before re-indexing
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+#[derive(serde::Serialize, Clone)]
+struct IndexUpdatedPayload {
+    updated: usize,
+    removed: usize,
+    total: usize,
+}
+
+/// Keeps the Songs folder watch alive, dropping it stops the background thread.
+pub struct SongsWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+pub fn start(app_handle: AppHandle, songs_path: &Path) -> notify::Result<SongsWatcher> {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })?;
+    watcher.watch(songs_path, RecursiveMode::Recursive)?;
+    println!("Watching '{}' for changes...", songs_path.display());
+
+    thread::spawn(move || {
+        let mut manifest = None;
+        // the channel closes once the watcher is dropped
+        while let Ok(first) = rx.recv() {
+            let mut changed_paths = HashSet::new();
+            collect_paths(first, &mut changed_paths);
+            loop {
+                match rx.recv_timeout(DEBOUNCE) {
+                    Ok(res) => collect_paths(res, &mut changed_paths),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            if !changed_paths.is_empty() {
+                let changed_paths: Vec<PathBuf> = changed_paths.into_iter().collect();
+                update_index(&app_handle, &changed_paths, &mut manifest);
+            }
+        }
+    });
+
+    Ok(SongsWatcher { _watcher: watcher })
+}
+
+/// Replaces the running watcher (if any) with one for `songs_path`.
+pub fn restart(app_handle: &AppHandle, songs_path: &Path) {
+    let state: tauri::State<AppState> = app_handle.state();
+    let mut watcher_guard = state.watcher.lock().unwrap();
+    *watcher_guard = None;
+    match start(app_handle.clone(), songs_path) {
+        Ok(watcher) => *watcher_guard = Some(watcher),
+        Err(e) => eprintln!("Failed to watch '{}': {}", songs_path.display(), e),
+    }
+}
+
+fn collect_paths(res: notify::Result<Event>, changed_paths: &mut HashSet<PathBuf>) {
+    let Ok(event) = res else {
+        return;
+    };
+    if matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) {
+        changed_paths.extend(event.paths.into_iter().filter(|path| is_relevant(path)));
+    }
+}
+
+// audio/images/storyboards change too, only .osu files and (removed) folders matter
+fn is_relevant(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "osu") || !path.is_file()
+}
+
+/// The manifest as of `generation`, kept between updates so it isn't read from
+/// disk for every change.
+struct CachedManifest {
+    generation: u64,
+    entries: Vec<ManifestEntry>,
+}
+
+fn update_index(
+    app_handle: &AppHandle,
+    changed_paths: &[PathBuf],
+    manifest: &mut Option<CachedManifest>,
+) {
+    let state: tauri::State<AppState> = app_handle.state();
+    loop {
+        let generation = state.index_generation.load(Ordering::SeqCst);
+        if manifest
+            .as_ref()
+            .map_or(true, |m| m.generation != generation)
+        {
+            // a manual indexing run might be writing it right now
+            let _indexing_guard = state.indexing_lock.lock().unwrap();
+            match core::indexer::load_manifest(&state.db_path) {
+                Ok(entries) => {
+                    *manifest = Some(CachedManifest {
+                        generation: state.index_generation.load(Ordering::SeqCst),
+                        entries,
+                    })
+                }
+                Err(e) => {
+                    eprintln!("Failed to update index: {}", e);
+                    return;
+                }
+            }
+        }
+        let cached = manifest.as_mut().unwrap();
+
+        // parsing and clustering run without the lock, so they don't hold up a manual run
+        let update = match core::indexer::prepare_changes(&mut cached.entries, changed_paths) {
+            Ok(Some(update)) => update,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Failed to update index: {}", e);
+                *manifest = None;
+                return;
+            }
+        };
+
+        let _indexing_guard = state.indexing_lock.lock().unwrap();
+        if state.index_generation.load(Ordering::SeqCst) != cached.generation {
+            // a manual run replaced the index in the meantime, redo the changes on top of it
+            continue;
+        }
+        if let Err(e) = core::indexer::save_update(&state.db_path, &update, &cached.entries) {
+            eprintln!("Failed to update index: {}", e);
+            *manifest = None;
+            return;
+        }
+        cached.generation = state.index_generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let payload = IndexUpdatedPayload {
+            updated: update.updated,
+            removed: update.removed,
+            total: update.map_infos.len(),
+        };
+        *state.index.lock().unwrap() = update
+            .db
+            .map(|db| crate::LoadedIndex::new(db, update.map_infos, update.vectors));
+        // whatever was wrong with the old index, this one was just built
+        *state.index_outdated.lock().unwrap() = None;
+        println!(
+            "Index updated: {} new or changed, {} removed.",
+            payload.updated, payload.removed
+        );
+
+        if let Err(e) = app_handle.emit("index-updated", payload) {
+            eprintln!("Failed to emit index-updated event: {}", e);
+        }
+        return;
+    }
+}